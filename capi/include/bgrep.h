@@ -0,0 +1,64 @@
+/*
+ * C interface of binarygrep
+ *
+ * Handles are owned by the caller and released with the matching *_free function.  A searcher
+ * and the data passed to bgrep_search_buffer must outlive the matches created from them.
+ */
+
+#ifndef BGREP_H
+#define BGREP_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Parse the pattern in the extended syntax */
+#define BGREP_EXTENDED 1u
+/* Do not report matches that overlap a previously reported match */
+#define BGREP_NO_OVERLAP 2u
+
+typedef struct bgrep_searcher bgrep_searcher;
+typedef struct bgrep_matches bgrep_matches;
+typedef struct bgrep_error bgrep_error;
+
+/* Match of a pattern, bytes are valid till the next call of bgrep_matches_next */
+typedef struct bgrep_match {
+    uint64_t offset;
+    size_t len;
+    const uint8_t *bytes;
+} bgrep_match;
+
+/* Compile a pattern with the BGREP_* flags, NULL on error */
+bgrep_searcher *bgrep_searcher_new(const char *pattern, uint32_t flags, bgrep_error **error);
+size_t bgrep_searcher_capture_count(const bgrep_searcher *searcher);
+void bgrep_searcher_free(bgrep_searcher *searcher);
+
+/* Search len bytes at data */
+bgrep_matches *bgrep_search_buffer(const bgrep_searcher *searcher, const uint8_t *data,
+                                   size_t len);
+#if !defined(_WIN32)
+/* Search fd from its current position till its end, fd is not closed (only on unix) */
+bgrep_matches *bgrep_search_fd(const bgrep_searcher *searcher, int fd);
+#endif
+
+/* Store the next match in m, returns 1 for a match, 0 at the end and -1 on error */
+int bgrep_matches_next(bgrep_matches *matches, bgrep_match *m, bgrep_error **error);
+/*
+ * Store the range of the capture with the 1-based index n of the last match, relative to its
+ * first byte, returns 1 if the capture exists and 0 otherwise
+ */
+int bgrep_matches_capture(const bgrep_matches *matches, size_t n, size_t *start, size_t *len);
+void bgrep_matches_free(bgrep_matches *matches);
+
+/* Message of an error, valid till it is freed */
+const char *bgrep_error_message(const bgrep_error *error);
+void bgrep_error_free(bgrep_error *error);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
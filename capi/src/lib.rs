@@ -0,0 +1,248 @@
+//! C interface of binarygrep, declared in `include/bgrep.h`
+//!
+//! Handles returned by the functions are owned by the caller and released with the matching
+//! `*_free` function.
+
+use std::ffi::{CStr, CString, c_char, c_int};
+use std::ptr;
+
+use binarygrep::{BgrepError, Match, Searcher};
+
+/// Parse the pattern in the extended syntax
+pub const BGREP_EXTENDED: u32 = 1;
+/// Do not report matches that overlap a previously reported match
+pub const BGREP_NO_OVERLAP: u32 = 2;
+
+/// Error message returned through an out parameter
+pub struct Error {
+    message: CString,
+}
+
+/// Matches of a search and the last match returned by `bgrep_matches_next`
+pub struct MatchIter {
+    matches: Box<dyn Iterator<Item = Result<Match, BgrepError>>>,
+    current: Option<Match>,
+}
+
+/// Match as seen from C, the bytes are valid till the next call with the same `MatchIter`
+#[repr(C)]
+pub struct BgrepMatch {
+    pub offset: u64,
+    pub len: usize,
+    pub bytes: *const u8,
+}
+
+/// Store `err` in `error` unless it is NULL and return NULL
+unsafe fn fail<T>(error: *mut *mut Error, err: BgrepError) -> *mut T {
+    if !error.is_null() {
+        let message = CString::new(err.0.replace('\0', "")).unwrap_or_default();
+        unsafe { *error = Box::into_raw(Box::new(Error { message })) };
+    }
+    ptr::null_mut()
+}
+
+/// Compile `pattern` with the `BGREP_*` `flags`, NULL on error
+///
+/// # Safety
+///
+/// `pattern` must be a NUL-terminated string and `error` NULL or valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bgrep_searcher_new(
+    pattern: *const c_char,
+    flags: u32,
+    error: *mut *mut Error,
+) -> *mut Searcher {
+    if pattern.is_null() {
+        return unsafe { fail(error, BgrepError(String::from("Pattern is NULL"))) };
+    }
+    let pattern = match unsafe { CStr::from_ptr(pattern) }.to_str() {
+        Ok(pattern) => pattern,
+        Err(err) => {
+            let err = BgrepError(format!("Pattern is not valid UTF-8: {}", err));
+            return unsafe { fail(error, err) };
+        }
+    };
+    let searcher = Searcher::builder()
+        .pattern(pattern)
+        .extended(flags & BGREP_EXTENDED != 0)
+        .no_overlap(flags & BGREP_NO_OVERLAP != 0)
+        .build();
+    match searcher {
+        Ok(searcher) => Box::into_raw(Box::new(searcher)),
+        Err(err) => unsafe { fail(error, err) },
+    }
+}
+
+/// Number of captures of the pattern of `searcher`
+///
+/// # Safety
+///
+/// `searcher` must have been returned by `bgrep_searcher_new`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bgrep_searcher_capture_count(searcher: *const Searcher) -> usize {
+    unsafe { &*searcher }.capture_count()
+}
+
+/// # Safety
+///
+/// `searcher` must be NULL or have been returned by `bgrep_searcher_new`, and must not be used by
+/// any `MatchIter` afterwards.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bgrep_searcher_free(searcher: *mut Searcher) {
+    if !searcher.is_null() {
+        drop(unsafe { Box::from_raw(searcher) });
+    }
+}
+
+/// Search the `len` bytes at `data`
+///
+/// # Safety
+///
+/// `searcher` and `data` must stay valid till the returned `MatchIter` is freed.  `data` may only
+/// be NULL if `len` is 0.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bgrep_search_buffer(
+    searcher: *const Searcher,
+    data: *const u8,
+    len: usize,
+) -> *mut MatchIter {
+    let searcher = unsafe { &*searcher };
+    let data = if len == 0 {
+        &[]
+    } else {
+        unsafe { std::slice::from_raw_parts(data, len) }
+    };
+    new_match_iter(Box::new(searcher.search_slice(data)))
+}
+
+/// Search the file descriptor `fd` from its current position till its end
+///
+/// The file descriptor is not closed.  Only available on unix, the header hides the declaration
+/// on Windows.
+///
+/// # Safety
+///
+/// `searcher` and `fd` must stay valid till the returned `MatchIter` is freed.
+#[cfg(unix)]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bgrep_search_fd(searcher: *const Searcher, fd: c_int) -> *mut MatchIter {
+    use std::os::fd::FromRawFd;
+    let searcher = unsafe { &*searcher };
+    let f = std::mem::ManuallyDrop::new(unsafe { std::fs::File::from_raw_fd(fd) });
+    new_match_iter(Box::new(searcher.search_reader(FdReader(f))))
+}
+
+/// Reader of a file descriptor owned by the caller
+#[cfg(unix)]
+struct FdReader(std::mem::ManuallyDrop<std::fs::File>);
+
+#[cfg(unix)]
+impl std::io::Read for FdReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+fn new_match_iter(matches: Box<dyn Iterator<Item = Result<Match, BgrepError>>>) -> *mut MatchIter {
+    Box::into_raw(Box::new(MatchIter {
+        matches,
+        current: None,
+    }))
+}
+
+/// Store the next match in `m`
+///
+/// Returns 1 if there is a match, 0 at the end of the input and -1 on error.
+///
+/// # Safety
+///
+/// `iter` must have been returned by a search function, `m` must be valid for writes and `error`
+/// NULL or valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bgrep_matches_next(
+    iter: *mut MatchIter,
+    m: *mut BgrepMatch,
+    error: *mut *mut Error,
+) -> c_int {
+    let iter = unsafe { &mut *iter };
+    iter.current = None;
+    match iter.matches.next() {
+        Some(Ok(current)) => {
+            unsafe {
+                *m = BgrepMatch {
+                    offset: current.offset as u64,
+                    len: current.len,
+                    bytes: current.bytes.as_ptr(),
+                };
+            }
+            iter.current = Some(current);
+            1
+        }
+        Some(Err(err)) => {
+            unsafe { fail::<()>(error, err) };
+            -1
+        }
+        None => 0,
+    }
+}
+
+/// Store the range of the capture with the 1-based index `n` of the last match, relative to its
+/// first byte, in `start` and `len`
+///
+/// Returns 1 if the capture exists and 0 otherwise.
+///
+/// # Safety
+///
+/// `iter` must have been returned by a search function, `start` and `len` must be valid for
+/// writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bgrep_matches_capture(
+    iter: *const MatchIter,
+    n: usize,
+    start: *mut usize,
+    len: *mut usize,
+) -> c_int {
+    let iter = unsafe { &*iter };
+    let Some(range) = iter
+        .current
+        .as_ref()
+        .and_then(|m| m.captures.get(n.checked_sub(1)?))
+    else {
+        return 0;
+    };
+    unsafe {
+        *start = range.start;
+        *len = range.len();
+    }
+    1
+}
+
+/// # Safety
+///
+/// `iter` must be NULL or have been returned by a search function.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bgrep_matches_free(iter: *mut MatchIter) {
+    if !iter.is_null() {
+        drop(unsafe { Box::from_raw(iter) });
+    }
+}
+
+/// Message of `error`, valid till it is freed
+///
+/// # Safety
+///
+/// `error` must have been returned through an out parameter.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bgrep_error_message(error: *const Error) -> *const c_char {
+    unsafe { &*error }.message.as_ptr()
+}
+
+/// # Safety
+///
+/// `error` must be NULL or have been returned through an out parameter.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bgrep_error_free(error: *mut Error) {
+    if !error.is_null() {
+        drop(unsafe { Box::from_raw(error) });
+    }
+}
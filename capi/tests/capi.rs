@@ -0,0 +1,87 @@
+use std::ffi::{CStr, CString};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::ptr;
+
+use bgrep::*;
+
+/// Offsets of all matches of `pattern` in `data`
+fn search_offsets(pattern: &str, flags: u32, data: &[u8]) -> Vec<u64> {
+    let pattern = CString::new(pattern).unwrap();
+    let mut result = vec![];
+    unsafe {
+        let searcher = bgrep_searcher_new(pattern.as_ptr(), flags, ptr::null_mut());
+        assert!(!searcher.is_null());
+        let matches = bgrep_search_buffer(searcher, data.as_ptr(), data.len());
+        let mut m = BgrepMatch {
+            offset: 0,
+            len: 0,
+            bytes: ptr::null(),
+        };
+        while bgrep_matches_next(matches, &mut m, ptr::null_mut()) == 1 {
+            result.push(m.offset);
+        }
+        bgrep_matches_free(matches);
+        bgrep_searcher_free(searcher);
+    }
+    result
+}
+
+#[test]
+fn test_search_buffer() {
+    let data = [0xaa, 0xaa, 0xaa, 0xaa];
+    assert_eq!(search_offsets("aaaa", 0, &data), vec![0, 1, 2]);
+    assert_eq!(search_offsets("aaaa", BGREP_NO_OVERLAP, &data), vec![0, 2]);
+    assert_eq!(search_offsets("aa{3}$", BGREP_EXTENDED, &data), vec![1]);
+    assert_eq!(search_offsets("aa", 0, &[]), vec![]);
+}
+
+#[test]
+fn test_error() {
+    let pattern = CString::new("a.").unwrap();
+    let mut error = ptr::null_mut();
+    unsafe {
+        let searcher = bgrep_searcher_new(pattern.as_ptr(), 0, &mut error);
+        assert!(searcher.is_null());
+        assert!(!error.is_null());
+        let message = CStr::from_ptr(bgrep_error_message(error));
+        assert!(message.to_str().unwrap().contains("a."));
+        bgrep_error_free(error);
+        // The error is optional
+        assert!(bgrep_searcher_new(ptr::null(), 0, ptr::null_mut()).is_null());
+    }
+}
+
+/// Directory of the built libraries
+fn target_dir() -> PathBuf {
+    let exe = std::env::current_exe().unwrap();
+    // The test executable is in the `deps` directory
+    exe.parent().unwrap().parent().unwrap().to_path_buf()
+}
+
+// The harness uses bgrep_search_fd and POSIX file descriptors
+#[cfg(unix)]
+#[test]
+fn test_c_harness() {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let target_dir = target_dir();
+    let harness = Path::new(env!("CARGO_TARGET_TMPDIR")).join("bgrep_harness");
+    let status = Command::new(std::env::var("CC").unwrap_or_else(|_| String::from("cc")))
+        .arg(manifest_dir.join("tests/harness.c"))
+        .arg("-I")
+        .arg(manifest_dir.join("include"))
+        .arg("-L")
+        .arg(&target_dir)
+        .arg("-lbgrep")
+        .arg("-o")
+        .arg(&harness)
+        .status()
+        .expect("Cannot run the C compiler");
+    assert!(status.success());
+    let status = Command::new(&harness)
+        .arg(manifest_dir.join("../tests/testdata_783"))
+        .env("LD_LIBRARY_PATH", &target_dir)
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
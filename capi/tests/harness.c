@@ -0,0 +1,73 @@
+/* Exercises the C interface through the header, exits with a non-zero status on failure */
+
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <unistd.h>
+
+#include "bgrep.h"
+
+#define CHECK(cond)                                                    \
+    do {                                                               \
+        if (!(cond)) {                                                 \
+            fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__,     \
+                    __LINE__, #cond);                                  \
+            return 1;                                                  \
+        }                                                              \
+    } while (0)
+
+static int test_buffer(void) {
+    bgrep_error *error = NULL;
+    bgrep_searcher *searcher = bgrep_searcher_new("01 (.) 02", BGREP_EXTENDED, &error);
+    CHECK(searcher != NULL && error == NULL);
+    CHECK(bgrep_searcher_capture_count(searcher) == 1);
+
+    const uint8_t data[] = {0x00, 0x01, 0xaa, 0x02, 0x01, 0xbb, 0x02};
+    bgrep_matches *matches = bgrep_search_buffer(searcher, data, sizeof(data));
+    bgrep_match m;
+    size_t start, len;
+    CHECK(bgrep_matches_next(matches, &m, &error) == 1);
+    CHECK(m.offset == 1 && m.len == 3 && memcmp(m.bytes, data + 1, 3) == 0);
+    CHECK(bgrep_matches_capture(matches, 1, &start, &len) == 1);
+    CHECK(start == 1 && len == 1);
+    CHECK(bgrep_matches_capture(matches, 2, &start, &len) == 0);
+    CHECK(bgrep_matches_next(matches, &m, &error) == 1);
+    CHECK(m.offset == 4 && m.bytes[1] == 0xbb);
+    CHECK(bgrep_matches_next(matches, &m, &error) == 0);
+    bgrep_matches_free(matches);
+    bgrep_searcher_free(searcher);
+    return 0;
+}
+
+static int test_fd(const char *path) {
+    bgrep_searcher *searcher = bgrep_searcher_new("c3df", 0, NULL);
+    CHECK(searcher != NULL);
+    int fd = open(path, O_RDONLY);
+    CHECK(fd >= 0);
+    bgrep_matches *matches = bgrep_search_fd(searcher, fd);
+    bgrep_match m;
+    CHECK(bgrep_matches_next(matches, &m, NULL) == 1);
+    CHECK(m.offset == 0x256 && m.len == 2);
+    CHECK(bgrep_matches_next(matches, &m, NULL) == 0);
+    bgrep_matches_free(matches);
+    /* The file descriptor is still open */
+    CHECK(close(fd) == 0);
+    bgrep_searcher_free(searcher);
+    return 0;
+}
+
+static int test_error(void) {
+    bgrep_error *error = NULL;
+    CHECK(bgrep_searcher_new("0g", 0, &error) == NULL);
+    CHECK(error != NULL && strstr(bgrep_error_message(error), "0g") != NULL);
+    bgrep_error_free(error);
+    return 0;
+}
+
+int main(int argc, char **argv) {
+    if (argc != 2) {
+        fprintf(stderr, "usage: %s FILE\n", argv[0]);
+        return 2;
+    }
+    return test_buffer() || test_fd(argv[1]) || test_error();
+}
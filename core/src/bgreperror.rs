@@ -0,0 +1,15 @@
+use alloc::string::String;
+
+pub struct BgrepError(pub String);
+
+impl core::fmt::Display for BgrepError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl core::fmt::Debug for BgrepError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "{}", self)
+    }
+}
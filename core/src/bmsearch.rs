@@ -2,10 +2,14 @@
 // Translated C implementation from
 // https://en.wikipedia.org/wiki/Boyer%E2%80%93Moore_string-search_algorithm
 
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::ControlFlow;
+
 use crate::bgreperror::BgrepError;
-use crate::buffer::Buffer;
-use crate::search::Search;
+use crate::haystack::Haystack;
 use crate::search::decode_hex;
+use crate::search::{BufferMatch, Search, SearchOptions};
 
 const ALPHABET_LEN: usize = 256;
 
@@ -13,10 +17,11 @@ pub struct BoyerMooreSearch {
     delta1: Vec<isize>,
     delta2: Vec<isize>,
     pat: Vec<u8>,
+    options: SearchOptions,
 }
 
 impl BoyerMooreSearch {
-    fn search_next(&self, data: &Buffer, offset: usize) -> Option<(usize, usize)> {
+    fn search_next(&self, data: &Haystack, offset: usize) -> Option<(usize, usize)> {
         let patlen = self.pat.len();
 
         if patlen == 0 {
@@ -27,7 +32,7 @@ impl BoyerMooreSearch {
             return None;
         }
 
-        let mut i = offset as isize + patlen as isize - 1;
+        let mut i = (self.options.next_candidate(data, offset) + patlen - 1) as isize;
         while i < (data.active_size + patlen - 1) as isize {
             let mut j = patlen as isize - 1;
             if data.at(i).is_none() {
@@ -44,11 +49,14 @@ impl BoyerMooreSearch {
             if j < 0 {
                 return Some(((i + 1) as usize, patlen));
             }
-            let shift = std::cmp::max(
+            let shift = core::cmp::max(
                 self.delta1[data.at(i).unwrap() as usize],
                 self.delta2[j as usize],
             );
             i += shift;
+            // Windows starting at unaligned positions cannot produce a reportable match
+            let start = (i + 1) as usize - patlen;
+            i = (self.options.next_candidate(data, start) + patlen - 1) as isize;
         }
 
         None
@@ -56,7 +64,7 @@ impl BoyerMooreSearch {
 }
 
 impl Search for BoyerMooreSearch {
-    fn new(pattern_hex: &str) -> Result<BoyerMooreSearch, BgrepError> {
+    fn new(pattern_hex: &str, options: SearchOptions) -> Result<BoyerMooreSearch, BgrepError> {
         let pat = decode_hex(pattern_hex)?;
         let mut delta1 = vec![pat.len() as isize; ALPHABET_LEN];
         let mut delta2 = vec![0; pat.len()];
@@ -66,20 +74,26 @@ impl Search for BoyerMooreSearch {
             delta1,
             delta2,
             pat,
+            options,
         })
     }
 
-    fn search(&self, data: &Buffer, offset: usize) -> Vec<(usize, usize)> {
+    fn search_each(
+        &self,
+        data: &Haystack,
+        offset: usize,
+        found: &mut dyn FnMut(BufferMatch) -> ControlFlow<()>,
+    ) -> ControlFlow<()> {
         let mut start_at = offset;
-        let mut result = vec![];
-        loop {
-            if let Some((i, match_len)) = self.search_next(data, start_at) {
-                result.push((i, match_len));
-                start_at = i + 1;
+        while let Some((i, match_len)) = self.search_next(data, start_at) {
+            found(BufferMatch::new(i, match_len))?;
+            start_at = if self.options.no_overlap {
+                i + match_len
             } else {
-                return result;
-            }
+                i + 1
+            };
         }
+        ControlFlow::Continue(())
     }
 
     fn max_pattern_len(&self) -> usize {
@@ -0,0 +1,203 @@
+use alloc::string::{String, ToString};
+
+/// Type as which captured bytes are interpreted
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Decode {
+    U8,
+    I8,
+    U16le,
+    U16be,
+    I16le,
+    I16be,
+    U32le,
+    U32be,
+    I32le,
+    I32be,
+    U64le,
+    U64be,
+    I64le,
+    I64be,
+    F32le,
+    F32be,
+    F64le,
+    F64be,
+}
+
+impl Decode {
+    /// All types in the order they are listed to users
+    pub const ALL: [Decode; 18] = [
+        Decode::U8,
+        Decode::I8,
+        Decode::U16le,
+        Decode::U16be,
+        Decode::I16le,
+        Decode::I16be,
+        Decode::U32le,
+        Decode::U32be,
+        Decode::I32le,
+        Decode::I32be,
+        Decode::U64le,
+        Decode::U64be,
+        Decode::I64le,
+        Decode::I64be,
+        Decode::F32le,
+        Decode::F32be,
+        Decode::F64le,
+        Decode::F64be,
+    ];
+
+    /// Name of the type, e.g. `u16le`
+    pub fn name(&self) -> &'static str {
+        match self {
+            Decode::U8 => "u8",
+            Decode::I8 => "i8",
+            Decode::U16le => "u16le",
+            Decode::U16be => "u16be",
+            Decode::I16le => "i16le",
+            Decode::I16be => "i16be",
+            Decode::U32le => "u32le",
+            Decode::U32be => "u32be",
+            Decode::I32le => "i32le",
+            Decode::I32be => "i32be",
+            Decode::U64le => "u64le",
+            Decode::U64be => "u64be",
+            Decode::I64le => "i64le",
+            Decode::I64be => "i64be",
+            Decode::F32le => "f32le",
+            Decode::F32be => "f32be",
+            Decode::F64le => "f64le",
+            Decode::F64be => "f64be",
+        }
+    }
+
+    /// Type with the given name, ignoring case
+    pub fn from_name(name: &str) -> Option<Decode> {
+        Decode::ALL
+            .into_iter()
+            .find(|decode| decode.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Number of bytes of the type
+    pub fn size(&self) -> usize {
+        match self {
+            Decode::U8 | Decode::I8 => 1,
+            Decode::U16le | Decode::U16be | Decode::I16le | Decode::I16be => 2,
+            Decode::U32le
+            | Decode::U32be
+            | Decode::I32le
+            | Decode::I32be
+            | Decode::F32le
+            | Decode::F32be => 4,
+            Decode::U64le
+            | Decode::U64be
+            | Decode::I64le
+            | Decode::I64be
+            | Decode::F64le
+            | Decode::F64be => 8,
+        }
+    }
+
+    pub fn is_integer(&self) -> bool {
+        !matches!(
+            self,
+            Decode::F32le | Decode::F32be | Decode::F64le | Decode::F64be
+        )
+    }
+
+    /// Value of `bytes` if the type is an integer type, `None` otherwise or if their number does
+    /// not match the type
+    pub fn integer(&self, bytes: &[u8]) -> Option<i128> {
+        Some(match self {
+            Decode::U8 => u8::from_le_bytes(bytes.try_into().ok()?).into(),
+            Decode::I8 => i8::from_le_bytes(bytes.try_into().ok()?).into(),
+            Decode::U16le => u16::from_le_bytes(bytes.try_into().ok()?).into(),
+            Decode::U16be => u16::from_be_bytes(bytes.try_into().ok()?).into(),
+            Decode::I16le => i16::from_le_bytes(bytes.try_into().ok()?).into(),
+            Decode::I16be => i16::from_be_bytes(bytes.try_into().ok()?).into(),
+            Decode::U32le => u32::from_le_bytes(bytes.try_into().ok()?).into(),
+            Decode::U32be => u32::from_be_bytes(bytes.try_into().ok()?).into(),
+            Decode::I32le => i32::from_le_bytes(bytes.try_into().ok()?).into(),
+            Decode::I32be => i32::from_be_bytes(bytes.try_into().ok()?).into(),
+            Decode::U64le => u64::from_le_bytes(bytes.try_into().ok()?).into(),
+            Decode::U64be => u64::from_be_bytes(bytes.try_into().ok()?).into(),
+            Decode::I64le => i64::from_le_bytes(bytes.try_into().ok()?).into(),
+            Decode::I64be => i64::from_be_bytes(bytes.try_into().ok()?).into(),
+            Decode::F32le | Decode::F32be | Decode::F64le | Decode::F64be => return None,
+        })
+    }
+
+    /// Decimal representation of `bytes`, `None` if their number does not match the type
+    pub fn decode(&self, bytes: &[u8]) -> Option<String> {
+        Some(match self {
+            Decode::U8 => u8::from_le_bytes(bytes.try_into().ok()?).to_string(),
+            Decode::I8 => i8::from_le_bytes(bytes.try_into().ok()?).to_string(),
+            Decode::U16le => u16::from_le_bytes(bytes.try_into().ok()?).to_string(),
+            Decode::U16be => u16::from_be_bytes(bytes.try_into().ok()?).to_string(),
+            Decode::I16le => i16::from_le_bytes(bytes.try_into().ok()?).to_string(),
+            Decode::I16be => i16::from_be_bytes(bytes.try_into().ok()?).to_string(),
+            Decode::U32le => u32::from_le_bytes(bytes.try_into().ok()?).to_string(),
+            Decode::U32be => u32::from_be_bytes(bytes.try_into().ok()?).to_string(),
+            Decode::I32le => i32::from_le_bytes(bytes.try_into().ok()?).to_string(),
+            Decode::I32be => i32::from_be_bytes(bytes.try_into().ok()?).to_string(),
+            Decode::U64le => u64::from_le_bytes(bytes.try_into().ok()?).to_string(),
+            Decode::U64be => u64::from_be_bytes(bytes.try_into().ok()?).to_string(),
+            Decode::I64le => i64::from_le_bytes(bytes.try_into().ok()?).to_string(),
+            Decode::I64be => i64::from_be_bytes(bytes.try_into().ok()?).to_string(),
+            Decode::F32le => f32::from_le_bytes(bytes.try_into().ok()?).to_string(),
+            Decode::F32be => f32::from_be_bytes(bytes.try_into().ok()?).to_string(),
+            Decode::F64le => f64::from_le_bytes(bytes.try_into().ok()?).to_string(),
+            Decode::F64be => f64::from_be_bytes(bytes.try_into().ok()?).to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        let bytes = [0x80, 0x00, 0x00, 0xff];
+        assert_eq!(
+            Decode::U32le.decode(&bytes),
+            Some(String::from("4278190208"))
+        );
+        assert_eq!(
+            Decode::U32be.decode(&bytes),
+            Some(String::from("2147483903"))
+        );
+        assert_eq!(
+            Decode::I32le.decode(&bytes),
+            Some(String::from("-16777088"))
+        );
+        assert_eq!(Decode::I8.decode(&bytes[..1]), Some(String::from("-128")));
+        assert_eq!(Decode::U16be.decode(&bytes[2..]), Some(String::from("255")));
+        assert_eq!(
+            Decode::F32le.decode(&1.5f32.to_le_bytes()),
+            Some(String::from("1.5"))
+        );
+        assert_eq!(Decode::U16le.decode(&bytes), None);
+        assert_eq!(Decode::U8.decode(&[]), None);
+    }
+
+    #[test]
+    fn test_integer() {
+        assert_eq!(Decode::U16be.integer(&[0x01, 0x02]), Some(0x102));
+        assert_eq!(Decode::I16le.integer(&[0xff, 0xff]), Some(-1));
+        assert_eq!(Decode::U64le.integer(&[0xff; 8]), Some(u64::MAX as i128));
+        assert_eq!(Decode::U32le.integer(&[0x01, 0x02]), None);
+        assert_eq!(Decode::F32le.integer(&[0; 4]), None);
+        assert_eq!(Decode::I64be.size(), 8);
+        assert!(!Decode::F64be.is_integer());
+    }
+
+    #[test]
+    fn test_names() {
+        assert_eq!(Decode::from_name("u16le"), Some(Decode::U16le));
+        assert_eq!(Decode::from_name("F64BE"), Some(Decode::F64be));
+        assert_eq!(Decode::from_name("u24le"), None);
+        for decode in Decode::ALL {
+            assert_eq!(Decode::from_name(decode.name()), Some(decode));
+        }
+    }
+}
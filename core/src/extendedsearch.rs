@@ -0,0 +1,1255 @@
+use crate::bgreperror::BgrepError;
+use crate::decode::Decode;
+use crate::haystack::Haystack;
+use crate::search::{BufferMatch, Search, SearchOptions};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp;
+use core::ops::{ControlFlow, Range};
+use core::str::FromStr;
+
+/// Largest count that can be taken from a field, larger values do not match
+const MAX_FIELD_COUNT: usize = 1 << 20;
+
+pub struct ExtendedSearch {
+    pattern: Vec<PatternEntry>,
+    /// Ranges of the entries of `pattern` enclosed by each group, in the order of their opening
+    /// parentheses
+    groups: Vec<Range<usize>>,
+    /// Matches must start at the first byte of the input (`^`)
+    anchor_start: bool,
+    /// Matches must end at the last byte of the input (`$`)
+    anchor_end: bool,
+    options: SearchOptions,
+}
+
+#[derive(Debug, PartialEq)]
+enum PatternChar {
+    Value(Vec<u8>),
+    Wildcard,
+    /// The bytes captured by the group with the given 1-based index
+    Backref(usize),
+}
+
+/// Group that captures an integer, e.g. `(len:u16le)`
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Field {
+    /// 0-based index of the group
+    group: usize,
+    decode: Decode,
+}
+
+impl Field {
+    fn max_count(&self) -> usize {
+        let max_value = (1u128 << (8 * self.decode.size())) - 1;
+        cmp::min(MAX_FIELD_COUNT as u128, max_value) as usize
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct PatternEntry {
+    patternchar: PatternChar,
+    min_cnt: usize,
+    max_cnt: usize,
+    /// Field whose value is the count of the entry, e.g. `.{len}`
+    count: Option<Field>,
+}
+
+impl PatternEntry {
+    /// Wildcards and character sets capture the bytes they match
+    fn is_capture(&self) -> bool {
+        match &self.patternchar {
+            PatternChar::Value(charset) => charset.len() > 1,
+            PatternChar::Wildcard => true,
+            PatternChar::Backref(_) => false,
+        }
+    }
+}
+
+type Groups = Vec<Range<usize>>;
+
+fn parse_extended(pattern_input: &str) -> Result<(Vec<PatternEntry>, Groups), BgrepError> {
+    if !pattern_input.is_ascii() {
+        return Err(BgrepError(format!(
+            "Pattern contains non-ascii characters: {}",
+            pattern_input
+        )));
+    }
+    let pattern_str = pattern_input.replace(" ", "");
+    let pattern_char: Vec<char> = pattern_str.chars().collect();
+    let mut i = 0;
+    let mut result = Vec::new();
+    let mut groups: Groups = Vec::new();
+    // Indices of the groups whose closing parenthesis is still missing
+    let mut open_groups = Vec::new();
+    let mut fields: Vec<(String, Field)> = Vec::new();
+    while i < pattern_char.len() {
+        if pattern_char[i] == '('
+            && let Some((consumed, name, decode)) = parse_field(&pattern_str[i..])?
+        {
+            if fields.iter().any(|(other, _)| *other == name) {
+                return Err(BgrepError(format!("Duplicate field name: {}", name)));
+            }
+            fields.push((
+                name,
+                Field {
+                    group: groups.len(),
+                    decode,
+                },
+            ));
+            groups.push(result.len()..result.len() + 1);
+            result.push(PatternEntry {
+                patternchar: PatternChar::Wildcard,
+                min_cnt: decode.size(),
+                max_cnt: decode.size(),
+                count: None,
+            });
+            i += consumed;
+            if i < pattern_char.len() && pattern_char[i] == '{' {
+                return Err(BgrepError(String::from(
+                    "Quantifiers cannot be applied to groups",
+                )));
+            }
+            continue;
+        }
+        if pattern_char[i] == '(' {
+            open_groups.push(groups.len());
+            groups.push(result.len()..result.len());
+            i += 1;
+            continue;
+        }
+        if pattern_char[i] == ')' {
+            let Some(group) = open_groups.pop() else {
+                return Err(BgrepError(format!(
+                    "Unmatched ) at index {} of pattern {}",
+                    i, pattern_input
+                )));
+            };
+            groups[group].end = result.len();
+            i += 1;
+            if i < pattern_char.len() && pattern_char[i] == '{' {
+                return Err(BgrepError(String::from(
+                    "Quantifiers cannot be applied to groups",
+                )));
+            }
+            continue;
+        }
+        let mut patternentry = PatternEntry {
+            patternchar: PatternChar::Value(vec![]),
+            min_cnt: 1,
+            max_cnt: 1,
+            count: None,
+        };
+        if pattern_char[i].is_ascii_hexdigit() {
+            let consumed = parse_hex_byte(&pattern_str[i..], &mut patternentry)?;
+            i += consumed;
+        } else if pattern_char[i] == '.' {
+            patternentry.patternchar = PatternChar::Wildcard;
+            i += 1;
+        } else if pattern_char[i] == '[' {
+            let consumed = parse_character_set(&pattern_str[i..], &mut patternentry)?;
+            i += consumed;
+        } else if pattern_char[i] == '\\' {
+            let consumed = parse_backref(&pattern_str[i..], &mut patternentry)?;
+            let PatternChar::Backref(group) = patternentry.patternchar else {
+                unreachable!();
+            };
+            if group > groups.len() || open_groups.contains(&(group - 1)) {
+                return Err(BgrepError(format!(
+                    "Back-reference \\{} does not refer to a preceding group",
+                    group
+                )));
+            }
+            i += consumed;
+        } else {
+            return Err(BgrepError(format!(
+                "Unexpected charater at index {}: {}",
+                i, pattern_char[i]
+            )));
+        }
+        if i < pattern_char.len() && pattern_char[i] == '{' {
+            let consumed = parse_quantifier(&pattern_str[i..], &mut patternentry, &fields)?;
+            i += consumed;
+        }
+        result.push(patternentry);
+    }
+    if !open_groups.is_empty() {
+        return Err(BgrepError(String::from("Incomplete group.  Missing )")));
+    }
+    Ok((result, groups))
+}
+
+/// Parse a field `(name:type)` at the start of `pattern`
+///
+/// Returns `None` if `pattern` starts with a different group and otherwise the number of
+/// consumed characters, the name and the type of the field.
+fn parse_field(pattern: &str) -> Result<Option<(usize, String, Decode)>, BgrepError> {
+    let rest = &pattern[1..];
+    let name_len = rest
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .unwrap_or(rest.len());
+    if !rest[name_len..].starts_with(':') {
+        return Ok(None);
+    }
+    let name = &rest[..name_len];
+    if !is_field_name(name) {
+        return Err(BgrepError(format!("Invalid field name: {}", name)));
+    }
+    let rest = &rest[name_len + 1..];
+    let Some(end) = rest.find(')') else {
+        return Err(BgrepError(String::from("Incomplete field.  Missing )")));
+    };
+    let decode = Decode::from_name(&rest[..end])
+        .filter(|decode| decode.is_integer())
+        .ok_or_else(|| {
+            BgrepError(format!(
+                "Invalid type of field {}: {} (expected an integer type like u16le)",
+                name,
+                &rest[..end]
+            ))
+        })?;
+    Ok(Some((name_len + end + 3, String::from(name), decode)))
+}
+
+fn is_field_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+}
+
+fn parse_quantifier(
+    pattern: &str,
+    entry: &mut PatternEntry,
+    fields: &[(String, Field)],
+) -> Result<usize, BgrepError> {
+    if let Some(end) = pattern.find('}') {
+        let name = &pattern[1..end];
+        if is_field_name(name) {
+            let Some((_, field)) = fields.iter().find(|(other, _)| other == name) else {
+                return Err(BgrepError(format!(
+                    "Quantifier refers to unknown field: {}",
+                    name
+                )));
+            };
+            entry.min_cnt = 0;
+            entry.max_cnt = field.max_count();
+            entry.count = Some(*field);
+            return Ok(end + 1);
+        }
+        for (i, val) in pattern[1..end].split(',').enumerate() {
+            let cnt = usize::from_str(val).map_err(|err| {
+                BgrepError(format!(
+                    "Invalid decimal value in quantifier '{}': {}",
+                    pattern, err
+                ))
+            })?;
+            if i == 0 {
+                entry.min_cnt = cnt;
+                entry.max_cnt = cnt;
+            } else if i == 1 {
+                entry.max_cnt = cnt;
+            } else {
+                return Err(BgrepError(String::from(
+                    "Quantifiers must contain only one or two values",
+                )));
+            }
+        }
+        if entry.min_cnt > entry.max_cnt {
+            return Err(BgrepError(format!(
+                "Min quantifier {} larger than max quantifier {}.",
+                entry.min_cnt, entry.max_cnt
+            )));
+        }
+
+        Ok(end + 1)
+    } else {
+        Err(BgrepError(String::from(
+            "Incomplete quantifier.  Missing }",
+        )))
+    }
+}
+
+fn parse_character_set(pattern: &str, entry: &mut PatternEntry) -> Result<usize, BgrepError> {
+    if let Some(end) = pattern.find(']') {
+        for val in pattern[1..end].split(',') {
+            if val.len() != 2 {
+                return Err(BgrepError(format!(
+                    "Invalid entry in character set: {}",
+                    val
+                )));
+            }
+            _ = parse_hex_byte(val, entry)?;
+        }
+        Ok(end + 1)
+    } else {
+        Err(BgrepError(String::from("Incomplete set.  Missing ]")))
+    }
+}
+
+/// Parse a back-reference `\1` to `\9`, which consists of a single digit since spaces in
+/// patterns are ignored and the pattern may continue with a hexadecimal byte
+fn parse_backref(pattern: &str, entry: &mut PatternEntry) -> Result<usize, BgrepError> {
+    match pattern[1..].chars().next().and_then(|c| c.to_digit(10)) {
+        Some(group) if group > 0 => {
+            entry.patternchar = PatternChar::Backref(group as usize);
+            Ok(2)
+        }
+        _ => Err(BgrepError(format!(
+            "Invalid back-reference '{}', expected \\1 to \\9",
+            pattern
+        ))),
+    }
+}
+
+fn parse_hex_byte(pattern: &str, entry: &mut PatternEntry) -> Result<usize, BgrepError> {
+    if pattern.len() < 2 {
+        return Err(BgrepError(String::from(
+            "Format error: Digit character not followed by another digit character",
+        )));
+    }
+    let val = u8::from_str_radix(&pattern[0..2], 16)
+        .map_err(|err| BgrepError(format!("Invalid hex pattern '{}': {}", pattern, err)))?;
+    if let PatternChar::Value(charset) = &mut entry.patternchar {
+        charset.push(val);
+    } else {
+        return Err(BgrepError(String::from("Could not push to charset")));
+    }
+    Ok(2)
+}
+
+/// Whether `pattern` with the counts `cnt` matches at index `i` of `data`
+fn matches_single_pattern(
+    data: &Haystack,
+    i: usize,
+    pattern: &[PatternEntry],
+    cnt: &[usize],
+) -> bool {
+    let mut processed = 0;
+    for (j, patternentry) in pattern.iter().enumerate() {
+        for _ in 0..cnt[j] {
+            let Some(c_buf) = data.at((i + processed) as isize) else {
+                return false;
+            };
+            processed += 1;
+            if let PatternChar::Value(charset) = &patternentry.patternchar
+                && !charset.contains(&c_buf)
+            {
+                return false;
+            }
+            // else patternentry.patternchar must be PatternChar::Wildcard, and therefore,
+            // we do nothing and continue since this is equal to having a match
+        }
+    }
+    true
+}
+
+/// Merge consecutive pattern entries with the same patternchar into a single
+/// entry whose min/max counts are the sums of the originals.  This avoids
+/// enumerating redundant quantifier combinations that expand to the same
+/// effective byte pattern (e.g. `.{1,2}.{1,2}` becomes `.{2,4}`).  Entries
+/// separated by the boundary of a group are not merged.
+fn merge_consecutive(pattern: Vec<PatternEntry>, groups: Groups) -> (Vec<PatternEntry>, Groups) {
+    let is_boundary = |k: usize| groups.iter().any(|g| g.start == k || g.end == k);
+    let mut merged: Vec<PatternEntry> = Vec::new();
+    // Index of each entry of `pattern` in `merged`, valid for the group boundaries
+    let mut index = Vec::with_capacity(pattern.len() + 1);
+    for (k, entry) in pattern.into_iter().enumerate() {
+        index.push(merged.len());
+        if let Some(last) = merged.last_mut()
+            && last.patternchar == entry.patternchar
+            && last.count.is_none()
+            && entry.count.is_none()
+            && !is_boundary(k)
+        {
+            last.min_cnt += entry.min_cnt;
+            last.max_cnt += entry.max_cnt;
+            continue;
+        }
+        merged.push(entry);
+    }
+    index.push(merged.len());
+    let groups = groups
+        .iter()
+        .map(|g| index[g.start]..index[g.end])
+        .collect();
+    (merged, groups)
+}
+
+impl Search for ExtendedSearch {
+    fn new(pattern: &str, options: SearchOptions) -> Result<ExtendedSearch, BgrepError> {
+        let trimmed = pattern.trim();
+        let (anchor_start, trimmed) = match trimmed.strip_prefix('^') {
+            Some(rest) => (true, rest),
+            None => (false, trimmed),
+        };
+        let (anchor_end, trimmed) = match trimmed.strip_suffix('$') {
+            Some(rest) => (true, rest),
+            None => (false, trimmed),
+        };
+        if trimmed.contains(['^', '$']) {
+            return Err(BgrepError(format!(
+                "Anchors are only allowed at the start (^) and the end ($) of a pattern: {}",
+                pattern
+            )));
+        }
+        let (pattern, groups) = parse_extended(trimmed)?;
+        let (pattern, groups) = merge_consecutive(pattern, groups);
+        Ok(ExtendedSearch {
+            pattern,
+            groups,
+            anchor_start,
+            anchor_end,
+            options,
+        })
+    }
+
+    fn search_each(
+        &self,
+        data: &Haystack,
+        offset: usize,
+        found: &mut dyn FnMut(BufferMatch) -> ControlFlow<()>,
+    ) -> ControlFlow<()> {
+        if self.pattern.is_empty() || offset >= data.active_size {
+            return ControlFlow::Continue(());
+        }
+
+        // Indices at which matches may start
+        let candidates = if self.anchor_start {
+            if offset > 0 || !data.is_start() {
+                return ControlFlow::Continue(());
+            }
+            0..1
+        } else {
+            offset..data.active_size
+        };
+        // End of the last match, only advanced if overlapping matches are suppressed
+        let mut end = 0;
+        let first = self.options.next_candidate(data, candidates.start);
+        for i in (first..candidates.end).step_by(self.options.align) {
+            if i < end {
+                continue;
+            }
+            // Only the matches at a single index are collected, which bounds the memory
+            // independently of the number of matches
+            let mut matches = vec![];
+            if self.needs_backtracking() {
+                self.backtrack(data, i, 0, &mut vec![], &mut matches);
+            } else {
+                self.search_counts(data, i, &mut matches);
+            }
+            if self.anchor_end {
+                matches.retain(|m| data.is_eof() && (m.start + m.len) as isize == data.max_index);
+            }
+            if self.options.no_overlap {
+                // Keep the longest match at the leftmost position and continue after its end
+                if let Some(m) = matches
+                    .into_iter()
+                    .reduce(|a, b| if b.len > a.len { b } else { a })
+                {
+                    end = m.start + m.len;
+                    found(m)?;
+                }
+            } else {
+                for m in matches {
+                    found(m)?;
+                }
+            }
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn max_pattern_len(&self) -> usize {
+        // Maximum number of bytes matched by each entry
+        let mut max_len: Vec<usize> = vec![];
+        for p in &self.pattern {
+            let len = match p.patternchar {
+                PatternChar::Backref(group) => max_len[self.groups[group - 1].clone()].iter().sum(),
+                _ => 1,
+            };
+            max_len.push(len * p.max_cnt);
+        }
+        max_len.iter().sum()
+    }
+
+    /// Explicit groups capture the bytes matched by the entries they enclose.  Without groups,
+    /// each wildcard and character set captures the bytes it matched.  Consecutive equal
+    /// entries are merged and therefore form a single capture.
+    fn capture_count(&self) -> usize {
+        if self.has_groups() {
+            self.groups.len()
+        } else {
+            self.pattern.iter().filter(|p| p.is_capture()).count()
+        }
+    }
+
+    fn has_groups(&self) -> bool {
+        !self.groups.is_empty()
+    }
+
+    fn is_anchored_at_start(&self) -> bool {
+        self.anchor_start
+    }
+}
+
+impl ExtendedSearch {
+    /// Whether the pattern contains back-references or counts taken from fields
+    fn needs_backtracking(&self) -> bool {
+        self.pattern
+            .iter()
+            .any(|p| matches!(p.patternchar, PatternChar::Backref(_)) || p.count.is_some())
+    }
+
+    /// Append the matches at index `i` of `data` to `result`
+    fn search_counts(&self, data: &Haystack, i: usize, result: &mut Vec<BufferMatch>) {
+        // To iterate over all combinations of repeated characters, the currently selected
+        // combination is tracked in `cnt`.  This vector is increased in the following loop to
+        // contain all possible allowed combinations.
+        let mut cnt: Vec<usize> = self.pattern.iter().map(|p| p.min_cnt).collect();
+        'cnt_loop: loop {
+            if matches_single_pattern(data, i, &self.pattern, &cnt) {
+                result.push(BufferMatch {
+                    start: i,
+                    len: cnt.iter().sum(),
+                    captures: self.captures(&cnt),
+                });
+            }
+            for (j, patternentry) in self.pattern.iter().enumerate() {
+                if cnt[j] < patternentry.max_cnt {
+                    cnt[j] += 1;
+                    break;
+                } else {
+                    if j == self.pattern.len() - 1 {
+                        break 'cnt_loop;
+                    }
+                    cnt[j] = patternentry.min_cnt;
+                }
+            }
+        }
+    }
+
+    /// Try all counts for the entries following the entries with counts `cnt`, which matched
+    /// the first `processed` bytes at index `i` of `data`
+    fn backtrack(
+        &self,
+        data: &Haystack,
+        i: usize,
+        processed: usize,
+        cnt: &mut Vec<usize>,
+        result: &mut Vec<BufferMatch>,
+    ) {
+        let Some(patternentry) = self.pattern.get(cnt.len()) else {
+            result.push(BufferMatch {
+                start: i,
+                len: processed,
+                captures: self.captures(cnt),
+            });
+            return;
+        };
+        // Offset of the group of a back-reference and the number of bytes matched by a single
+        // repetition of the entry
+        let (group_start, len) = match patternentry.patternchar {
+            PatternChar::Backref(group) => {
+                let offsets = self.offsets(cnt);
+                let group = &self.groups[group - 1];
+                (
+                    offsets[group.start],
+                    offsets[group.end] - offsets[group.start],
+                )
+            }
+            _ => (0, 1),
+        };
+        let (min_cnt, max_cnt) = match patternentry.count {
+            Some(field) => {
+                let offsets = self.offsets(cnt);
+                let group = &self.groups[field.group];
+                let bytes: Option<Vec<u8>> = (offsets[group.start]..offsets[group.end])
+                    .map(|k| data.at((i + k) as isize))
+                    .collect();
+                match bytes.and_then(|bytes| field.decode.integer(&bytes)) {
+                    Some(value) if (0..=MAX_FIELD_COUNT as i128).contains(&value) => {
+                        (value as usize, value as usize)
+                    }
+                    _ => return,
+                }
+            }
+            None => (patternentry.min_cnt, patternentry.max_cnt),
+        };
+        let mut end = processed;
+        for n in 0..=max_cnt {
+            if n >= min_cnt {
+                cnt.push(n);
+                self.backtrack(data, i, end, cnt, result);
+                cnt.pop();
+                if len == 0 {
+                    // More repetitions of an empty back-reference match the same bytes
+                    break;
+                }
+            }
+            if n == max_cnt {
+                break;
+            }
+            let matched = (0..len).all(|k| {
+                let Some(c_buf) = data.at((i + end + k) as isize) else {
+                    return false;
+                };
+                match &patternentry.patternchar {
+                    PatternChar::Value(charset) => charset.contains(&c_buf),
+                    PatternChar::Wildcard => true,
+                    PatternChar::Backref(_) => {
+                        data.at((i + group_start + k) as isize) == Some(c_buf)
+                    }
+                }
+            });
+            if !matched {
+                break;
+            }
+            end += len;
+        }
+    }
+
+    /// Offset of each entry of the pattern within a match with counts `cnt`
+    ///
+    /// Back-references match the bytes of their group once per count.
+    fn offsets(&self, cnt: &[usize]) -> Vec<usize> {
+        let mut offsets = vec![0];
+        for (j, n) in cnt.iter().enumerate() {
+            let len = match self.pattern[j].patternchar {
+                PatternChar::Backref(group) => {
+                    let group = &self.groups[group - 1];
+                    offsets[group.end] - offsets[group.start]
+                }
+                _ => 1,
+            };
+            offsets.push(offsets[j] + n * len);
+        }
+        offsets
+    }
+
+    /// Ranges of the captures relative to the start of a match with counts `cnt`
+    fn captures(&self, cnt: &[usize]) -> Vec<Range<usize>> {
+        let offsets = self.offsets(cnt);
+        if self.has_groups() {
+            self.groups
+                .iter()
+                .map(|g| offsets[g.start]..offsets[g.end])
+                .collect()
+        } else {
+            self.pattern
+                .iter()
+                .enumerate()
+                .filter(|(_, p)| p.is_capture())
+                .map(|(j, _)| offsets[j]..offsets[j + 1])
+                .collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_valid() {
+        let input = String::from("630f29.5bde0881b7");
+        let expected = vec![
+            PatternEntry {
+                patternchar: PatternChar::Value(vec![0x63]),
+                min_cnt: 1,
+                max_cnt: 1,
+                count: None,
+            },
+            PatternEntry {
+                patternchar: PatternChar::Value(vec![0x0f]),
+                min_cnt: 1,
+                max_cnt: 1,
+                count: None,
+            },
+            PatternEntry {
+                patternchar: PatternChar::Value(vec![0x29]),
+                min_cnt: 1,
+                max_cnt: 1,
+                count: None,
+            },
+            PatternEntry {
+                patternchar: PatternChar::Wildcard,
+                min_cnt: 1,
+                max_cnt: 1,
+                count: None,
+            },
+            PatternEntry {
+                patternchar: PatternChar::Value(vec![0x5b]),
+                min_cnt: 1,
+                max_cnt: 1,
+                count: None,
+            },
+            PatternEntry {
+                patternchar: PatternChar::Value(vec![0xde]),
+                min_cnt: 1,
+                max_cnt: 1,
+                count: None,
+            },
+            PatternEntry {
+                patternchar: PatternChar::Value(vec![0x08]),
+                min_cnt: 1,
+                max_cnt: 1,
+                count: None,
+            },
+            PatternEntry {
+                patternchar: PatternChar::Value(vec![0x81]),
+                min_cnt: 1,
+                max_cnt: 1,
+                count: None,
+            },
+            PatternEntry {
+                patternchar: PatternChar::Value(vec![0xb7]),
+                min_cnt: 1,
+                max_cnt: 1,
+                count: None,
+            },
+        ];
+        match parse_extended(&input) {
+            Err(_) => panic!(),
+            Ok((result, groups)) => {
+                assert_eq!(result, expected);
+                assert!(groups.is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_valid_quantifier() {
+        let input = String::from("63{2}0f{1,1}29{4}.{5,10}5bde{3,4}0881b7{7,12}");
+        let expected = vec![
+            PatternEntry {
+                patternchar: PatternChar::Value(vec![0x63]),
+                min_cnt: 2,
+                max_cnt: 2,
+                count: None,
+            },
+            PatternEntry {
+                patternchar: PatternChar::Value(vec![0x0f]),
+                min_cnt: 1,
+                max_cnt: 1,
+                count: None,
+            },
+            PatternEntry {
+                patternchar: PatternChar::Value(vec![0x29]),
+                min_cnt: 4,
+                max_cnt: 4,
+                count: None,
+            },
+            PatternEntry {
+                patternchar: PatternChar::Wildcard,
+                min_cnt: 5,
+                max_cnt: 10,
+                count: None,
+            },
+            PatternEntry {
+                patternchar: PatternChar::Value(vec![0x5b]),
+                min_cnt: 1,
+                max_cnt: 1,
+                count: None,
+            },
+            PatternEntry {
+                patternchar: PatternChar::Value(vec![0xde]),
+                min_cnt: 3,
+                max_cnt: 4,
+                count: None,
+            },
+            PatternEntry {
+                patternchar: PatternChar::Value(vec![0x08]),
+                min_cnt: 1,
+                max_cnt: 1,
+                count: None,
+            },
+            PatternEntry {
+                patternchar: PatternChar::Value(vec![0x81]),
+                min_cnt: 1,
+                max_cnt: 1,
+                count: None,
+            },
+            PatternEntry {
+                patternchar: PatternChar::Value(vec![0xb7]),
+                min_cnt: 7,
+                max_cnt: 12,
+                count: None,
+            },
+        ];
+        match parse_extended(&input) {
+            Err(_) => panic!(),
+            Ok((result, groups)) => {
+                assert_eq!(result, expected);
+                assert!(groups.is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_valid_character_set() {
+        let input = String::from("[63,0f,29].5b[de]08[81,b7]");
+        let expected = vec![
+            PatternEntry {
+                patternchar: PatternChar::Value(vec![0x63, 0x0f, 0x29]),
+                min_cnt: 1,
+                max_cnt: 1,
+                count: None,
+            },
+            PatternEntry {
+                patternchar: PatternChar::Wildcard,
+                min_cnt: 1,
+                max_cnt: 1,
+                count: None,
+            },
+            PatternEntry {
+                patternchar: PatternChar::Value(vec![0x5b]),
+                min_cnt: 1,
+                max_cnt: 1,
+                count: None,
+            },
+            PatternEntry {
+                patternchar: PatternChar::Value(vec![0xde]),
+                min_cnt: 1,
+                max_cnt: 1,
+                count: None,
+            },
+            PatternEntry {
+                patternchar: PatternChar::Value(vec![0x08]),
+                min_cnt: 1,
+                max_cnt: 1,
+                count: None,
+            },
+            PatternEntry {
+                patternchar: PatternChar::Value(vec![0x81, 0xb7]),
+                min_cnt: 1,
+                max_cnt: 1,
+                count: None,
+            },
+        ];
+        match parse_extended(&input) {
+            Err(_) => panic!(),
+            Ok((result, groups)) => {
+                assert_eq!(result, expected);
+                assert!(groups.is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_valid_combined() {
+        let input = String::from("[63,0f,29]{3,10}.{2}5b[de]{7,20}08{2}[81,b7]{3,9}");
+        let expected = vec![
+            PatternEntry {
+                patternchar: PatternChar::Value(vec![0x63, 0x0f, 0x29]),
+                min_cnt: 3,
+                max_cnt: 10,
+                count: None,
+            },
+            PatternEntry {
+                patternchar: PatternChar::Wildcard,
+                min_cnt: 2,
+                max_cnt: 2,
+                count: None,
+            },
+            PatternEntry {
+                patternchar: PatternChar::Value(vec![0x5b]),
+                min_cnt: 1,
+                max_cnt: 1,
+                count: None,
+            },
+            PatternEntry {
+                patternchar: PatternChar::Value(vec![0xde]),
+                min_cnt: 7,
+                max_cnt: 20,
+                count: None,
+            },
+            PatternEntry {
+                patternchar: PatternChar::Value(vec![0x08]),
+                min_cnt: 2,
+                max_cnt: 2,
+                count: None,
+            },
+            PatternEntry {
+                patternchar: PatternChar::Value(vec![0x81, 0xb7]),
+                min_cnt: 3,
+                max_cnt: 9,
+                count: None,
+            },
+        ];
+        match parse_extended(&input) {
+            Err(_) => panic!(),
+            Ok((result, groups)) => {
+                assert_eq!(result, expected);
+                assert!(groups.is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_invalid_length() {
+        let input = String::from("[63,0f,29]{3,10}.{2}5bf[de]{7,20}08{2}[81,b7]{3,9}");
+        match parse_extended(&input) {
+            Err(_) => (),
+            Ok(_) => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_parse_invalid_hex_char() {
+        let input = String::from("[63,0f,29]{3,10}.{2}5z[de]{7,20}08{2}[81,b7]{3,9}");
+        match parse_extended(&input) {
+            Err(_) => (),
+            Ok(_) => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_parse_invalid_quantifier_empty() {
+        let input = String::from("[63,0f,29]{3,10}.{}5f[de]{7,20}08{2}[81,b7]{3,9}");
+        match parse_extended(&input) {
+            Err(_) => (),
+            Ok(_) => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_parse_invalid_quantifier_minempty() {
+        let input = String::from("[63,0f,29]{3,10}.{,2}5f[de]{7,20}08{2}[81,b7]{3,9}");
+        match parse_extended(&input) {
+            Err(_) => (),
+            Ok(_) => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_parse_invalid_quantifier_maxempty() {
+        let input = String::from("[63,0f,29]{3,10}.{2,}5f[de]{7,20}08{2}[81,b7]{3,9}");
+        match parse_extended(&input) {
+            Err(_) => (),
+            Ok(_) => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_parse_invalid_quantifier_too_many() {
+        let input = String::from("[63,0f,29]{3,10}.{2,3,4}5f[de]{7,20}08{2}[81,b7]{3,9}");
+        match parse_extended(&input) {
+            Err(_) => (),
+            Ok(_) => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_parse_invalid_quantifier_min_greater_max() {
+        let input = String::from("[63,0f,29]{10,3}.{2}5f[de]{7,20}08{2}[81,b7]{3,9}");
+        match parse_extended(&input) {
+            Err(_) => (),
+            Ok(_) => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_parse_invalid_quantifier_missing_close_bracket() {
+        let input = String::from("[63,0f,29]{10,3.{2}5f[de]{7,20}08{2}[81,b7]{3,9}");
+        match parse_extended(&input) {
+            Err(_) => (),
+            Ok(_) => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_parse_invalid_quantifier_missing_open_bracket() {
+        let input = String::from("[63,0f,29]10,3}.{2}5f[de]{7,20}08{2}[81,b7]{3,9}");
+        match parse_extended(&input) {
+            Err(_) => (),
+            Ok(_) => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_parse_invalid_set_missing_comma() {
+        let input = String::from("[63,0f29]{3,10}.{2}5b[de]{7,20}08{2}[81,b7]{3,9}");
+        match parse_extended(&input) {
+            Err(_) => (),
+            Ok(_) => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_parse_invalid_set_empty_entry() {
+        let input = String::from("[63,0f,29]{3,10}.{2}5b[de,]{7,20}08{2}[81,b7]{3,9}");
+        match parse_extended(&input) {
+            Err(_) => (),
+            Ok(_) => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_parse_invalid_set_non_hex() {
+        let input = String::from("[63,0f,29]{3,10}.{2}5b[dz,]{7,20}08{2}[81,b7]{3,9}");
+        match parse_extended(&input) {
+            Err(_) => (),
+            Ok(_) => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_parse_invalid_set_missing_close_bracket() {
+        let input = String::from("[63,0f,29]{3,10}.{2}5b[dz,]{7,20}08{2}[81,b7{3,9}");
+        match parse_extended(&input) {
+            Err(_) => (),
+            Ok(_) => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_parse_invalid_set_missing_open_bracket() {
+        let input = String::from("[63,0f,29]{3,10}.{2}5b[dz,]{7,20}08{2}81,b7]{3,9}");
+        match parse_extended(&input) {
+            Err(_) => (),
+            Ok(_) => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_max_length() {
+        let input = String::from("[63,0f,29]{3,10}.{2}5b[de]{7,20}08{2}[81,b7]{3,9}");
+        if let Ok(extendedsearch) = ExtendedSearch::new(&input, SearchOptions::default()) {
+            assert_eq!(extendedsearch.max_pattern_len(), 44);
+        } else {
+            panic!();
+        }
+    }
+
+    fn search_all(pattern: &str, data: &[u8]) -> Vec<BufferMatch> {
+        let extendedsearch = ExtendedSearch::new(pattern, SearchOptions::default()).unwrap();
+        extendedsearch.search(&Haystack::new(data), 0)
+    }
+
+    #[test]
+    fn test_captures() {
+        let input = String::from("01 .{1,3} 02 [03,04]{2} 05{0,2} .");
+        let extendedsearch = ExtendedSearch::new(&input, SearchOptions::default()).unwrap();
+        assert_eq!(extendedsearch.capture_count(), 3);
+        assert!(!extendedsearch.has_groups());
+        assert_eq!(
+            search_all(&input, &[0x01, 0xaa, 0xbb, 0x02, 0x04, 0x03, 0x05, 0x06]),
+            vec![
+                BufferMatch {
+                    start: 0,
+                    len: 7,
+                    captures: vec![1..3, 4..6, 6..7]
+                },
+                BufferMatch {
+                    start: 0,
+                    len: 8,
+                    captures: vec![1..3, 4..6, 7..8]
+                }
+            ]
+        );
+        assert_eq!(
+            search_all(&input, &[0x01, 0x02, 0x02, 0x02, 0x03, 0x03, 0xff]),
+            vec![BufferMatch {
+                start: 0,
+                len: 7,
+                captures: vec![1..3, 4..6, 6..7]
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parse_groups() {
+        let (pattern, groups) = parse_extended("4d5a (.{2} (05 [06,07])) () 08").unwrap();
+        assert_eq!(pattern.len(), 6);
+        assert_eq!(groups, vec![2..5, 3..5, 5..5]);
+        assert!(parse_extended("(01").is_err());
+        assert!(parse_extended("01)").is_err());
+        assert!(parse_extended("(01){2}").is_err());
+    }
+
+    #[test]
+    fn test_merge_groups() {
+        let input = String::from(". (.{2}) . 05 (05)");
+        let extendedsearch = ExtendedSearch::new(&input, SearchOptions::default()).unwrap();
+        assert_eq!(extendedsearch.pattern.len(), 5);
+        assert_eq!(extendedsearch.groups, vec![1..2, 4..5]);
+        assert_eq!(extendedsearch.capture_count(), 2);
+        assert!(extendedsearch.has_groups());
+        assert_eq!(
+            search_all(&input, &[0xaa, 0x01, 0x02, 0x03, 0x05, 0x05]),
+            vec![BufferMatch {
+                start: 0,
+                len: 6,
+                captures: vec![1..3, 5..6]
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parse_backrefs() {
+        let (pattern, groups) = parse_extended("(.{2}) \\1 \\1{2,3} 05").unwrap();
+        assert_eq!(groups, vec![0..1]);
+        assert_eq!(pattern[1].patternchar, PatternChar::Backref(1));
+        assert_eq!(pattern[3].patternchar, PatternChar::Value(vec![0x05]));
+        let extendedsearch =
+            ExtendedSearch::new("(.{2}) \\1 \\1{2,3} 05", SearchOptions::default()).unwrap();
+        assert_eq!(extendedsearch.pattern.len(), 3);
+        assert_eq!(extendedsearch.max_pattern_len(), 11);
+        assert!(parse_extended("\\1 (.)").is_err());
+        assert!(parse_extended("(. \\1)").is_err());
+        assert!(parse_extended("(.) \\2").is_err());
+        assert!(parse_extended("(.) \\0").is_err());
+        assert!(parse_extended("(.) \\").is_err());
+    }
+
+    #[test]
+    #[allow(clippy::single_range_in_vec_init)]
+    fn test_search_backrefs() {
+        let data = [0x01, 0xaa, 0x02, 0xaa, 0x03, 0xbb, 0x04, 0xcc, 0xbb];
+        assert_eq!(
+            search_all("(.) . \\1", &data),
+            vec![BufferMatch {
+                start: 1,
+                len: 3,
+                captures: vec![0..1]
+            }]
+        );
+        assert_eq!(
+            search_all("[01,03] (.) .{1,3} \\1", &data),
+            vec![
+                BufferMatch {
+                    start: 0,
+                    len: 4,
+                    captures: vec![1..2]
+                },
+                BufferMatch {
+                    start: 4,
+                    len: 5,
+                    captures: vec![1..2]
+                }
+            ]
+        );
+        // The length of the group determines the length of the back-reference
+        let data = [0x02, 0xab, 0xcd, 0x00, 0xab, 0xcd, 0xab, 0xcd];
+        assert_eq!(
+            search_all("02 (.{1,2}) 00 \\1{2}", &data),
+            vec![BufferMatch {
+                start: 0,
+                len: 8,
+                captures: vec![1..3]
+            }]
+        );
+        assert_eq!(search_all("(.) \\1", &data), vec![]);
+        let data = [0x07, 0x07, 0x07];
+        assert_eq!(
+            search_all("(()07) \\2{0,2} \\1", &data)
+                .iter()
+                .map(|m| (m.start, m.len))
+                .collect::<Vec<_>>(),
+            vec![(0, 2), (1, 2)]
+        );
+    }
+
+    #[test]
+    fn test_search_backrefs_no_overlap() {
+        let extendedsearch = ExtendedSearch::new(
+            "(.) \\1{1,3}",
+            SearchOptions {
+                no_overlap: true,
+                ..SearchOptions::default()
+            },
+        )
+        .unwrap();
+        let data = [0x05, 0x05, 0x05, 0x06, 0x06, 0x06, 0x06, 0x06];
+        assert_eq!(
+            extendedsearch
+                .search(&Haystack::new(&data), 0)
+                .iter()
+                .map(|m| (m.start, m.len))
+                .collect::<Vec<_>>(),
+            vec![(0, 3), (3, 4)]
+        );
+    }
+
+    #[test]
+    fn test_anchors() {
+        let data = [0x7f, 0x45, 0x7f, 0x45, 0x00, 0x7f, 0x45, 0x00, 0x7f, 0x45];
+        let starts = |pattern| {
+            search_all(pattern, &data)
+                .iter()
+                .map(|m| (m.start, m.len))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(starts("7f 45").len(), 4);
+        assert_eq!(starts("^7f 45"), vec![(0, 2)]);
+        assert_eq!(starts(" ^ 7f 45 $ "), vec![]);
+        assert_eq!(starts("7f 45$"), vec![(8, 2)]);
+        assert_eq!(starts("^.{1,20}$"), vec![(0, 10)]);
+        assert_eq!(starts("(7f) .{0,3} \\1 45$"), vec![(5, 5)]);
+        // A chunk that is not the start of the input cannot match an anchor at the start
+        let extendedsearch = ExtendedSearch::new("^7f 45", SearchOptions::default()).unwrap();
+        let haystack = Haystack::from_parts((&data, &[]), -2, 8, 2, false, true);
+        assert_eq!(extendedsearch.search(&haystack, 0), vec![]);
+        assert!(ExtendedSearch::new("7f ^ 45", SearchOptions::default()).is_err());
+        assert!(ExtendedSearch::new("7f $ 45", SearchOptions::default()).is_err());
+        assert!(ExtendedSearch::new("^^7f", SearchOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_parse_fields() {
+        let (pattern, groups) = parse_extended("01 (len:u16le) .{len} (n:U8) 00{n}").unwrap();
+        assert_eq!(groups, vec![1..2, 3..4]);
+        assert_eq!(
+            pattern[2],
+            PatternEntry {
+                patternchar: PatternChar::Wildcard,
+                min_cnt: 0,
+                max_cnt: 65535,
+                count: Some(Field {
+                    group: 0,
+                    decode: Decode::U16le
+                }),
+            }
+        );
+        assert_eq!(pattern[3].min_cnt, 1);
+        assert_eq!(pattern[4].count.unwrap().group, 1);
+        let extendedsearch =
+            ExtendedSearch::new("(a:u8) .{a} (b:u32be) .{b}", SearchOptions::default()).unwrap();
+        assert_eq!(
+            extendedsearch.max_pattern_len(),
+            1 + 255 + 4 + MAX_FIELD_COUNT
+        );
+        assert!(parse_extended("(len:u16le) .{size}").is_err());
+        assert!(parse_extended(".{len} (len:u16le)").is_err());
+        assert!(parse_extended("(len:f32le)").is_err());
+        assert!(parse_extended("(len:u24le)").is_err());
+        assert!(parse_extended("(1a:u8)").is_err());
+        assert!(parse_extended("(a:u8) (a:u8)").is_err());
+        assert!(parse_extended("(a:u8){2}").is_err());
+        assert!(parse_extended("(a:u8").is_err());
+    }
+
+    #[test]
+    fn test_search_fields() {
+        let data = [
+            0x01, 0x02, 0x00, 0xaa, 0xbb, 0x01, 0x03, 0x00, 0xcc, 0x01, 0xff, 0xff,
+        ];
+        let matches = search_all("01 (len:u16le) .{len}", &data);
+        assert_eq!(
+            matches.iter().map(|m| (m.start, m.len)).collect::<Vec<_>>(),
+            vec![(0, 5), (5, 6)]
+        );
+        assert_eq!(matches[0].captures, vec![1..3]);
+        // The count applies to back-references as well
+        let data = [0x02, 0xab, 0xab, 0xab, 0x03, 0xcd, 0xcd, 0xcd];
+        assert_eq!(
+            search_all("(n:u8) (.) \\2{n}", &data)
+                .iter()
+                .map(|m| (m.start, m.len))
+                .collect::<Vec<_>>(),
+            vec![(0, 4)]
+        );
+        // Negative values do not match
+        assert_eq!(search_all("(n:i8) .{n}", &[0xff, 0xaa]), vec![]);
+        assert_eq!(search_all("(n:i8) .{n}", &[0x00]).len(), 1);
+    }
+}
@@ -0,0 +1,129 @@
+/// Chunk of an input that is searched at once
+///
+/// Matches may start at the indices `[0, active_size)` and extend up to `max_index`.  Negative
+/// indices down to `min_index` give access to bytes preceding the chunk.  The bytes are stored
+/// in two slices that are treated as consecutive data, e.g. the two parts of a ring buffer.
+pub struct Haystack<'a> {
+    first: &'a [u8],
+    second: &'a [u8],
+    pub active_size: usize,
+    /// Absolute offset of index 0 in the input
+    pub position: usize,
+    pub min_index: isize,
+    pub max_index: isize,
+    is_start: bool,
+    is_eof: bool,
+}
+
+impl<'a> Haystack<'a> {
+    /// Haystack of a complete input
+    pub fn new(data: &'a [u8]) -> Haystack<'a> {
+        Haystack {
+            first: data,
+            second: &[],
+            active_size: data.len(),
+            position: 0,
+            min_index: 0,
+            max_index: data.len() as isize,
+            is_start: true,
+            is_eof: true,
+        }
+    }
+
+    /// Haystack of a chunk of an input whose bytes from index `min_index` on are `first`
+    /// followed by `second`
+    ///
+    /// Index 0 is at absolute offset `position` of the input.  `is_start` and `is_eof` tell
+    /// whether no bytes precede `min_index` and follow the last byte, respectively.
+    pub fn from_parts(
+        (first, second): (&'a [u8], &'a [u8]),
+        min_index: isize,
+        active_size: usize,
+        position: usize,
+        is_start: bool,
+        is_eof: bool,
+    ) -> Haystack<'a> {
+        Haystack {
+            first,
+            second,
+            active_size,
+            position,
+            min_index,
+            max_index: min_index + (first.len() + second.len()) as isize,
+            is_start,
+            is_eof,
+        }
+    }
+
+    /// Is the end of the input the end of this chunk
+    pub fn is_eof(&self) -> bool {
+        self.is_eof
+    }
+
+    /// Is index 0 the first byte of the input
+    pub fn is_start(&self) -> bool {
+        self.is_start
+    }
+
+    /// Returns the value at offset `i` if this is a valid index
+    #[inline]
+    pub fn at(&self, i: isize) -> Option<u8> {
+        if i < self.min_index || i >= self.max_index {
+            return None;
+        }
+        let k = (i - self.min_index) as usize;
+        if k < self.first.len() {
+            Some(self.first[k])
+        } else {
+            Some(self.second[k - self.first.len()])
+        }
+    }
+
+    /// Return the view `[first, last)` of the haystack as two slices that should be treated as
+    /// consecutive data
+    pub fn view(&self, first: isize, last: isize) -> Option<(&'a [u8], &'a [u8])> {
+        if first > last || first < self.min_index || last > self.max_index {
+            return None;
+        }
+        let first = (first - self.min_index) as usize;
+        let last = (last - self.min_index) as usize;
+        let split = self.first.len();
+        if last <= split {
+            Some((&self.first[first..last], &[]))
+        } else if first >= split {
+            Some((&self.second[first - split..last - split], &[]))
+        } else {
+            Some((&self.first[first..], &self.second[..last - split]))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_at() {
+        let haystack = Haystack::from_parts((&[1, 2, 3], &[4, 5]), -2, 2, 10, false, true);
+        assert_eq!(haystack.max_index, 3);
+        assert_eq!(haystack.at(-3), None);
+        assert_eq!(haystack.at(-2), Some(1));
+        assert_eq!(haystack.at(1), Some(4));
+        assert_eq!(haystack.at(2), Some(5));
+        assert_eq!(haystack.at(3), None);
+        let haystack = Haystack::new(&[7, 8]);
+        assert_eq!(haystack.at(0), Some(7));
+        assert!(haystack.is_start() && haystack.is_eof());
+    }
+
+    #[test]
+    fn test_view() {
+        let haystack = Haystack::from_parts((&[1, 2, 3], &[4, 5]), -2, 2, 10, false, true);
+        assert_eq!(haystack.view(-2, 0), Some((&[1, 2][..], &[][..])));
+        assert_eq!(haystack.view(0, 3), Some((&[3][..], &[4, 5][..])));
+        assert_eq!(haystack.view(1, 3), Some((&[4, 5][..], &[][..])));
+        assert_eq!(haystack.view(1, 1), Some((&[][..], &[][..])));
+        assert_eq!(haystack.view(0, 4), None);
+        assert_eq!(haystack.view(2, 1), None);
+    }
+}
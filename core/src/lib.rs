@@ -0,0 +1,30 @@
+//! Pattern parser and search engines of binarygrep
+//!
+//! The crate only requires `alloc`.  Inputs are searched as [`Haystack`]s that borrow their
+//! bytes, so memory such as flash can be searched in place.
+#![cfg_attr(not(test), no_std)]
+
+extern crate alloc;
+
+mod bgreperror;
+pub use bgreperror::BgrepError;
+
+mod haystack;
+pub use haystack::Haystack;
+
+mod search;
+pub use search::{BufferMatch, Search, SearchOptions, decode_hex};
+
+mod bmsearch;
+pub use bmsearch::BoyerMooreSearch;
+
+mod extendedsearch;
+pub use extendedsearch::ExtendedSearch;
+
+mod decode;
+pub use decode::Decode;
+
+#[cfg(feature = "wasm")]
+mod wasm;
+#[cfg(feature = "wasm")]
+pub use wasm::{Pattern, SearchMatch};
@@ -0,0 +1,188 @@
+use alloc::format;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::{ControlFlow, Range};
+
+use crate::bgreperror::BgrepError;
+use crate::haystack::Haystack;
+
+/// Options that restrict where the search engines report matches
+#[derive(Clone, Copy)]
+pub struct SearchOptions {
+    /// Only report matches at absolute offsets `align_offset + k * align`
+    pub align: usize,
+    pub align_offset: usize,
+    /// Resume the search after the end of each match instead of after its first byte
+    pub no_overlap: bool,
+}
+
+impl Default for SearchOptions {
+    fn default() -> SearchOptions {
+        SearchOptions {
+            align: 1,
+            align_offset: 0,
+            no_overlap: false,
+        }
+    }
+}
+
+impl SearchOptions {
+    /// Returns the first index `>= i` of `data` at which a match may start
+    pub fn next_candidate(&self, data: &Haystack, i: usize) -> usize {
+        let misalignment =
+            (data.position + i + self.align - self.align_offset % self.align) % self.align;
+        if misalignment == 0 {
+            i
+        } else {
+            i + self.align - misalignment
+        }
+    }
+}
+
+/// Match of a pattern at index `start` of a haystack
+#[derive(Clone, Debug, PartialEq)]
+pub struct BufferMatch {
+    pub start: usize,
+    pub len: usize,
+    /// Ranges of the captures relative to `start`
+    pub captures: Vec<Range<usize>>,
+}
+
+impl BufferMatch {
+    /// Match without captures
+    pub fn new(start: usize, len: usize) -> BufferMatch {
+        BufferMatch {
+            start,
+            len,
+            captures: vec![],
+        }
+    }
+}
+
+pub trait Search {
+    fn new(pat: &str, options: SearchOptions) -> Result<Self, BgrepError>
+    where
+        Self: Sized;
+    /// Call `found` with each match starting at an index `>= offset` of the current chunk of
+    /// `data` in the order of their start, stop when it returns `ControlFlow::Break`
+    fn search_each(
+        &self,
+        data: &Haystack,
+        offset: usize,
+        found: &mut dyn FnMut(BufferMatch) -> ControlFlow<()>,
+    ) -> ControlFlow<()>;
+    /// Matches starting at an index `>= offset` of the current chunk of `data`
+    fn search(&self, data: &Haystack, offset: usize) -> Vec<BufferMatch> {
+        let mut result = vec![];
+        let _ = self.search_each(data, offset, &mut |m| {
+            result.push(m);
+            ControlFlow::Continue(())
+        });
+        result
+    }
+    fn max_pattern_len(&self) -> usize;
+    /// Number of captures of the pattern
+    fn capture_count(&self) -> usize {
+        0
+    }
+    /// Whether the captures are groups written explicitly in the pattern
+    fn has_groups(&self) -> bool {
+        false
+    }
+    /// Whether matches can only start at the first byte of the input
+    fn is_anchored_at_start(&self) -> bool {
+        false
+    }
+}
+
+pub fn decode_hex(pattern_input: &str) -> Result<Vec<u8>, BgrepError> {
+    if !pattern_input.is_ascii() {
+        return Err(BgrepError(format!(
+            "Hex pattern contains non-ascii characters: {}",
+            pattern_input
+        )));
+    }
+    let pattern = pattern_input.replace(" ", "");
+    if !pattern.len().is_multiple_of(2) {
+        return Err(BgrepError(format!(
+            "Hex pattern does not have even amount of characters: {}",
+            pattern_input
+        )));
+    }
+    (0..(pattern.len() / 2))
+        .map(|i| {
+            u8::from_str_radix(&pattern[(2 * i)..(2 * i + 2)], 16).map_err(|err| {
+                BgrepError(format!("Invalid hex pattern '{}': {}", pattern_input, err))
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_candidate() {
+        let data = [0u8; 32];
+        let haystack = Haystack::from_parts((&data, &[]), 0, 16, 5, true, false);
+        let options = SearchOptions {
+            align: 4,
+            align_offset: 0,
+            no_overlap: false,
+        };
+        assert_eq!(options.next_candidate(&haystack, 0), 3);
+        assert_eq!(options.next_candidate(&haystack, 3), 3);
+        assert_eq!(options.next_candidate(&haystack, 4), 7);
+        let options = SearchOptions {
+            align: 4,
+            align_offset: 6,
+            no_overlap: false,
+        };
+        assert_eq!(options.next_candidate(&haystack, 0), 1);
+        assert_eq!(options.next_candidate(&haystack, 2), 5);
+        assert_eq!(SearchOptions::default().next_candidate(&haystack, 9), 9);
+    }
+
+    #[test]
+    fn test_decode_hex_valid() {
+        let hex_string = String::from("b081133bbf0cb70a288734");
+        let expected_hexbytes: Vec<u8> = vec![
+            0xb0, 0x81, 0x13, 0x3b, 0xbf, 0x0c, 0xb7, 0x0a, 0x28, 0x87, 0x34,
+        ];
+        match decode_hex(&hex_string) {
+            Err(_) => panic!(),
+            Ok(hexbytes) => assert_eq!(hexbytes, expected_hexbytes),
+        }
+    }
+
+    #[test]
+    fn test_decode_hex_spaces() {
+        let hex_string = String::from("b 08 11 33b bf0 cb7 0a28 8734");
+        let expected_hexbytes: Vec<u8> = vec![
+            0xb0, 0x81, 0x13, 0x3b, 0xbf, 0x0c, 0xb7, 0x0a, 0x28, 0x87, 0x34,
+        ];
+        match decode_hex(&hex_string) {
+            Err(_) => panic!(),
+            Ok(hexbytes) => assert_eq!(hexbytes, expected_hexbytes),
+        }
+    }
+
+    #[test]
+    fn test_decode_hex_invalid_length() {
+        let hex_string = String::from("b081133bbf0cb70a28873");
+        match decode_hex(&hex_string) {
+            Err(_) => (),
+            Ok(_) => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_decode_hex_invalid_characters() {
+        let hex_string = String::from("b081133zbf0cb70a288734");
+        match decode_hex(&hex_string) {
+            Err(_) => (),
+            Ok(_) => panic!(),
+        }
+    }
+}
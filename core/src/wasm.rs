@@ -0,0 +1,110 @@
+use alloc::boxed::Box;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use wasm_bindgen::prelude::*;
+
+use crate::bgreperror::BgrepError;
+use crate::bmsearch::BoyerMooreSearch;
+use crate::extendedsearch::ExtendedSearch;
+use crate::haystack::Haystack;
+use crate::search::{Search, SearchOptions};
+
+/// Compiled search pattern, e.g. `new Pattern("4d5a .{2} (.{2})", true)` in JavaScript
+#[wasm_bindgen]
+pub struct Pattern {
+    search: Box<dyn Search>,
+}
+
+#[wasm_bindgen]
+impl Pattern {
+    /// Compile `pattern`, which uses the extended syntax if `extended` is set
+    #[wasm_bindgen(constructor)]
+    pub fn new(pattern: &str, extended: bool) -> Result<Pattern, JsError> {
+        compile(pattern, extended).map_err(|err| JsError::new(&err.0))
+    }
+
+    /// Number of captures of each match
+    #[wasm_bindgen(getter, js_name = captureCount)]
+    pub fn capture_count(&self) -> usize {
+        self.search.capture_count()
+    }
+
+    /// Matches in `data`, a `Uint8Array` in JavaScript, in the order of their offsets
+    pub fn search(&self, data: &[u8]) -> Vec<SearchMatch> {
+        let mut matches = vec![];
+        let _ = self.search.search_each(&Haystack::new(data), 0, &mut |m| {
+            matches.push(SearchMatch {
+                offset: m.start,
+                length: m.len,
+                captures: m
+                    .captures
+                    .iter()
+                    .flat_map(|c| [m.start + c.start, m.start + c.end])
+                    .collect(),
+            });
+            core::ops::ControlFlow::Continue(())
+        });
+        matches
+    }
+}
+
+fn compile(pattern: &str, extended: bool) -> Result<Pattern, BgrepError> {
+    let search: Box<dyn Search> = if extended {
+        Box::new(ExtendedSearch::new(pattern, SearchOptions::default())?)
+    } else {
+        Box::new(BoyerMooreSearch::new(pattern, SearchOptions::default())?)
+    };
+    Ok(Pattern { search })
+}
+
+/// Match of a [`Pattern`]
+#[wasm_bindgen]
+pub struct SearchMatch {
+    offset: usize,
+    length: usize,
+    captures: Vec<usize>,
+}
+
+#[wasm_bindgen]
+impl SearchMatch {
+    /// Offset of the first byte of the match
+    #[wasm_bindgen(getter)]
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn length(&self) -> usize {
+        self.length
+    }
+
+    /// Start and end offsets of each capture, e.g. `data.subarray(c[0], c[1])` is the first
+    /// capture of the match `c` was taken from
+    #[wasm_bindgen(getter)]
+    pub fn captures(&self) -> Vec<usize> {
+        self.captures.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search() {
+        let pattern = compile("01 (.) 02", true).unwrap();
+        assert_eq!(pattern.capture_count(), 1);
+        let matches = pattern.search(&[0x00, 0x01, 0xaa, 0x02, 0x01, 0xbb, 0x02]);
+        assert_eq!(
+            matches
+                .iter()
+                .map(|m| (m.offset(), m.length(), m.captures()))
+                .collect::<Vec<_>>(),
+            vec![(1, 3, vec![2, 3]), (4, 3, vec![5, 6])]
+        );
+        let pattern = compile("aaaa", false).unwrap();
+        assert_eq!(pattern.search(&[0xaa; 4]).len(), 3);
+        assert!(compile("a.", false).is_err());
+    }
+}
@@ -0,0 +1,34 @@
+//! Tests of the JavaScript interface, run in Node.js without a browser
+#![cfg(all(target_arch = "wasm32", feature = "wasm"))]
+
+use binarygrep_core::Pattern;
+use wasm_bindgen_test::*;
+
+#[wasm_bindgen_test]
+fn test_search() {
+    let pattern = Pattern::new("4d5a .{2} (.{2})", true).unwrap();
+    assert_eq!(pattern.capture_count(), 1);
+    let data = b"..MZ\x00\x00\x80\x01..MZ";
+    let matches = pattern.search(data);
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0].offset(), 2);
+    assert_eq!(matches[0].length(), 6);
+    assert_eq!(matches[0].captures(), vec![6, 8]);
+}
+
+#[wasm_bindgen_test]
+fn test_plain() {
+    let pattern = Pattern::new("0102", false).unwrap();
+    let offsets: Vec<usize> = pattern
+        .search(&[0x01, 0x02, 0x01, 0x02, 0x02, 0x01, 0x02])
+        .iter()
+        .map(|m| m.offset())
+        .collect();
+    assert_eq!(offsets, vec![0, 2, 5]);
+}
+
+#[wasm_bindgen_test]
+fn test_invalid() {
+    assert!(Pattern::new("0g", false).is_err());
+    assert!(Pattern::new("01{2", true).is_err());
+}
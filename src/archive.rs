@@ -0,0 +1,295 @@
+use std::io::{self, Read, Seek};
+
+use binarygrep_core::BgrepError;
+
+/// Offset of the magic bytes of tar archives
+const TAR_MAGIC_OFFSET: usize = 257;
+
+/// Size of the header of a cpio member in the "new ASCII" format
+const CPIO_NEWC_HEADER_LEN: usize = 110;
+
+/// Size of the header of a cpio member in the "old ASCII" format
+const CPIO_ODC_HEADER_LEN: usize = 76;
+
+/// Name of the last member of a cpio archive
+const CPIO_TRAILER: &str = "TRAILER!!!";
+
+/// Called with the name and the data of each regular file in an archive
+pub type Visit<'a> = dyn FnMut(&str, &mut dyn Read) -> Result<(), BgrepError> + 'a;
+
+/// Archive formats recognized by the magic bytes at the start of the data
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    Zip,
+    Tar,
+    Cpio,
+}
+
+impl Kind {
+    /// Format of the data starting with `header`, `None` if it is not an archive
+    pub fn detect(header: &[u8]) -> Option<Kind> {
+        if header.starts_with(b"PK\x03\x04") || header.starts_with(b"PK\x05\x06") {
+            Some(Kind::Zip)
+        } else if header[TAR_MAGIC_OFFSET.min(header.len())..].starts_with(b"ustar") {
+            Some(Kind::Tar)
+        } else if header.starts_with(b"070701")
+            || header.starts_with(b"070702")
+            || header.starts_with(b"070707")
+        {
+            Some(Kind::Cpio)
+        } else {
+            None
+        }
+    }
+
+    /// Call `visit` for each regular file in the archive `reader` named `filename`
+    ///
+    /// Zip archives are read into memory since their index is at the end.
+    pub fn for_each_member(
+        self,
+        filename: &str,
+        reader: &mut dyn Read,
+        visit: &mut Visit,
+    ) -> Result<(), BgrepError> {
+        match self {
+            Kind::Zip => {
+                let mut data = vec![];
+                reader
+                    .read_to_end(&mut data)
+                    .map_err(|err| BgrepError(format!("Error while reading: {}", err)))?;
+                zip_members(filename, io::Cursor::new(data), visit)
+            }
+            Kind::Tar => tar_members(filename, reader, visit),
+            Kind::Cpio => cpio_members(filename, reader, visit),
+        }
+    }
+}
+
+/// Call `visit` for each regular file in the zip archive `reader` named `filename`
+pub fn zip_members(
+    filename: &str,
+    reader: impl Read + Seek,
+    visit: &mut Visit,
+) -> Result<(), BgrepError> {
+    let invalid = |err: zip::result::ZipError| {
+        BgrepError(format!("Invalid zip archive '{}': {}", filename, err))
+    };
+    let mut archive = zip::ZipArchive::new(reader).map_err(invalid)?;
+    for i in 0..archive.len() {
+        let mut member = archive.by_index(i).map_err(invalid)?;
+        if member.is_file() {
+            let name = member.name().to_string();
+            visit(&name, &mut member)?;
+        }
+    }
+    Ok(())
+}
+
+/// Call `visit` for each regular file in the tar archive `reader` named `filename`
+fn tar_members(filename: &str, reader: &mut dyn Read, visit: &mut Visit) -> Result<(), BgrepError> {
+    let invalid =
+        |err: io::Error| BgrepError(format!("Invalid tar archive '{}': {}", filename, err));
+    let mut archive = tar::Archive::new(reader);
+    for member in archive.entries().map_err(invalid)? {
+        let mut member = member.map_err(invalid)?;
+        if member.header().entry_type().is_file() {
+            let name = member
+                .path()
+                .map_err(invalid)?
+                .to_string_lossy()
+                .into_owned();
+            visit(&name, &mut member)?;
+        }
+    }
+    Ok(())
+}
+
+/// Call `visit` for each regular file in the cpio archive `reader` named `filename`
+///
+/// Supports the "new ASCII" and the "old ASCII" format.
+fn cpio_members(
+    filename: &str,
+    reader: &mut dyn Read,
+    visit: &mut Visit,
+) -> Result<(), BgrepError> {
+    let invalid =
+        |message: &str| BgrepError(format!("Invalid cpio archive '{}': {}", filename, message));
+    let read_error = |err: io::Error| BgrepError(format!("Error while reading: {}", err));
+    loop {
+        let mut magic = [0; 6];
+        reader.read_exact(&mut magic).map_err(read_error)?;
+        // Fields of the new format are hexadecimal and aligned to 4 bytes, those of the old format
+        // are octal and unaligned
+        let (header_len, radix, alignment) = match &magic {
+            b"070701" | b"070702" => (CPIO_NEWC_HEADER_LEN, 16, 4),
+            b"070707" => (CPIO_ODC_HEADER_LEN, 8, 1),
+            _ => return Err(invalid("unknown header")),
+        };
+        let mut header = vec![0; header_len];
+        header[..6].copy_from_slice(&magic);
+        reader.read_exact(&mut header[6..]).map_err(read_error)?;
+        let field = |start: usize, len: usize| {
+            std::str::from_utf8(&header[start..start + len])
+                .ok()
+                .and_then(|field| u64::from_str_radix(field, radix).ok())
+                .ok_or_else(|| invalid("invalid header field"))
+        };
+        let (mode, name_len, size) = if radix == 16 {
+            (field(14, 8)?, field(94, 8)?, field(54, 8)?)
+        } else {
+            (field(18, 6)?, field(59, 6)?, field(65, 11)?)
+        };
+        let mut name = vec![0; name_len as usize];
+        reader.read_exact(&mut name).map_err(read_error)?;
+        skip(reader, padding(header_len as u64 + name_len, alignment)).map_err(read_error)?;
+        let name = String::from_utf8_lossy(name.strip_suffix(&[0]).unwrap_or(&name)).into_owned();
+        if name == CPIO_TRAILER {
+            return Ok(());
+        }
+        let mut member = reader.take(size);
+        if mode & 0o170000 == 0o100000 {
+            visit(&name, &mut member)?;
+        }
+        // The member may not have been read till its end
+        skip(&mut member, u64::MAX).map_err(read_error)?;
+        skip(reader, padding(size, alignment)).map_err(read_error)?;
+    }
+}
+
+/// Number of bytes needed to align `len` bytes to `alignment`
+fn padding(len: u64, alignment: u64) -> u64 {
+    (alignment - len % alignment) % alignment
+}
+
+/// Skip up to `len` bytes of `reader`
+fn skip(reader: &mut dyn Read, len: u64) -> io::Result<u64> {
+    io::copy(&mut reader.take(len), &mut io::sink())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Names and data of the members of an archive
+    fn members(kind: Kind, data: &[u8]) -> Result<Vec<(String, Vec<u8>)>, BgrepError> {
+        let mut members = vec![];
+        kind.for_each_member("test", &mut &data[..], &mut |name, member| {
+            let mut data = vec![];
+            member.read_to_end(&mut data).unwrap();
+            members.push((name.to_string(), data));
+            Ok(())
+        })?;
+        Ok(members)
+    }
+
+    fn cpio_newc(members: &[(&str, u32, &[u8])]) -> Vec<u8> {
+        let mut data = vec![];
+        for &(name, mode, content) in members.iter().chain([&("TRAILER!!!", 0, &b""[..])]) {
+            data.extend(b"070701");
+            let len = content.len() as u32;
+            let name_len = name.len() as u32 + 1;
+            for field in [0, mode, 0, 0, 1, 0, len, 0, 0, 0, 0, name_len, 0] {
+                data.extend(format!("{:08x}", field).bytes());
+            }
+            data.extend(name.bytes());
+            data.push(0);
+            data.resize(data.len() + padding(data.len() as u64, 4) as usize, 0);
+            data.extend(content);
+            data.resize(data.len() + padding(data.len() as u64, 4) as usize, 0);
+        }
+        data
+    }
+
+    #[test]
+    fn test_cpio() {
+        let data = cpio_newc(&[
+            ("dir", 0o040755, b""),
+            ("dir/a.bin", 0o100644, b"\x01\x02\x03"),
+            ("b", 0o100644, b"\xaa\xbb\xcc\xdd\xee"),
+        ]);
+        assert_eq!(Kind::detect(&data), Some(Kind::Cpio));
+        assert_eq!(
+            members(Kind::Cpio, &data).unwrap(),
+            [
+                ("dir/a.bin".to_string(), vec![0x01, 0x02, 0x03]),
+                ("b".to_string(), vec![0xaa, 0xbb, 0xcc, 0xdd, 0xee])
+            ]
+        );
+        // Members are skipped if they are not read
+        let mut names = vec![];
+        Kind::Cpio
+            .for_each_member("test", &mut &data[..], &mut |name, _| {
+                names.push(name.to_string());
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(names, ["dir/a.bin", "b"]);
+        let mut odc = vec![];
+        for (name, mode, content) in [
+            ("a", 0o100644, &b"\xaa\xbb\xcc"[..]),
+            ("TRAILER!!!", 0, b""),
+        ] {
+            odc.extend(
+                format!(
+                    "070707{:06o}{:06o}{:06o}{:06o}{:06o}{:06o}{:06o}{:011o}{:06o}{:011o}{}\0",
+                    0,
+                    0,
+                    mode,
+                    0,
+                    0,
+                    1,
+                    0,
+                    0,
+                    name.len() + 1,
+                    content.len(),
+                    name
+                )
+                .bytes(),
+            );
+            odc.extend(content);
+        }
+        assert_eq!(
+            members(Kind::Cpio, &odc).unwrap(),
+            [("a".to_string(), vec![0xaa, 0xbb, 0xcc])]
+        );
+        assert!(members(Kind::Cpio, &data[..data.len() - 20]).is_err());
+        assert!(members(Kind::Cpio, b"070701zzzzzzzz").is_err());
+    }
+
+    #[test]
+    fn test_tar() {
+        let mut builder = tar::Builder::new(vec![]);
+        let mut header = tar::Header::new_ustar();
+        header.set_size(3);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "dir/a.bin", &[0x01, 0x02, 0x03][..])
+            .unwrap();
+        let data = builder.into_inner().unwrap();
+        assert_eq!(Kind::detect(&data), Some(Kind::Tar));
+        assert_eq!(
+            members(Kind::Tar, &data).unwrap(),
+            [("dir/a.bin".to_string(), vec![0x01, 0x02, 0x03])]
+        );
+    }
+
+    #[test]
+    fn test_zip() {
+        let mut writer = zip::ZipWriter::new(io::Cursor::new(vec![]));
+        writer
+            .start_file("a.bin", zip::write::SimpleFileOptions::default())
+            .unwrap();
+        io::Write::write_all(&mut writer, &[0x01, 0x02, 0x03]).unwrap();
+        writer
+            .add_directory("dir", zip::write::SimpleFileOptions::default())
+            .unwrap();
+        let data = writer.finish().unwrap().into_inner();
+        assert_eq!(Kind::detect(&data), Some(Kind::Zip));
+        assert_eq!(
+            members(Kind::Zip, &data).unwrap(),
+            [("a.bin".to_string(), vec![0x01, 0x02, 0x03])]
+        );
+        assert!(members(Kind::Zip, &data[..10]).is_err());
+        assert_eq!(Kind::detect(b"\x7fELF"), None);
+    }
+}
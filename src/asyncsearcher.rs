@@ -0,0 +1,95 @@
+use futures_util::stream::{self, Stream};
+use tokio::io::AsyncRead;
+
+use crate::searcher::{Match, MatchesState, Searcher};
+use binarygrep_core::BgrepError;
+
+impl Searcher {
+    /// Stream of the matches in `reader`
+    ///
+    /// The input is searched in the same chunks and by the same engines as by
+    /// [`Searcher::search_reader`], so both report the same matches.
+    pub fn search_async_reader<'a, R: AsyncRead + Unpin + 'a>(
+        &'a self,
+        reader: R,
+    ) -> impl Stream<Item = Result<Match, BgrepError>> + 'a {
+        let state = MatchesState::new(self.reader_buffer_size());
+        stream::unfold((reader, state), move |(mut reader, mut state)| async move {
+            loop {
+                if let Some(m) = state.pending.pop_front() {
+                    return Some((m, (reader, state)));
+                }
+                if state.done {
+                    return None;
+                }
+                let read = state.buffer.read_async(&mut reader).await;
+                state.search_chunk(self, read);
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::StreamExt;
+
+    const FIXTURES: [&str; 2] = ["tests/testdata_783", "tests/subdir/testdata_1200"];
+
+    /// Compare the matches of the async and the sync search in each fixture
+    async fn assert_same_matches(searcher: &Searcher) {
+        for fixture in FIXTURES {
+            let expected: Vec<Match> = searcher
+                .search_path(fixture)
+                .unwrap()
+                .map(|m| m.unwrap())
+                .collect();
+            let f = tokio::fs::File::open(fixture).await.unwrap();
+            let matches: Vec<Match> = searcher
+                .search_async_reader(f)
+                .map(|m| m.unwrap())
+                .collect()
+                .await;
+            assert_eq!(matches, expected, "{}", fixture);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_search_async_reader() {
+        for (pattern, extended) in [
+            ("c3df", false),
+            ("00", false),
+            ("^.{4}", true),
+            ("(.) \\1", true),
+            ("[00,ff] .{1,3} [00,ff]", true),
+            (".{2}$", true),
+        ] {
+            let searcher = Searcher::builder()
+                .pattern(pattern)
+                .extended(extended)
+                .build()
+                .unwrap();
+            assert_same_matches(&searcher).await;
+        }
+        let searcher = Searcher::builder()
+            .pattern(".{1,2}")
+            .extended(true)
+            .align(4)
+            .no_overlap(true)
+            .build()
+            .unwrap();
+        assert_same_matches(&searcher).await;
+    }
+
+    #[tokio::test]
+    async fn test_search_async_reader_slice() {
+        let searcher = Searcher::builder().pattern("0102").build().unwrap();
+        let data = [0x01, 0x02, 0x01, 0x02, 0x02, 0x01, 0x02];
+        let offsets: Vec<usize> = searcher
+            .search_async_reader(&data[..])
+            .map(|m| m.unwrap().offset)
+            .collect()
+            .await;
+        assert_eq!(offsets, vec![0, 2, 5]);
+    }
+}
@@ -1,4 +1,8 @@
+use std::cmp;
 use std::io;
+use std::ops::Range;
+
+use binarygrep_core::Haystack;
 
 #[derive(PartialEq, Eq)]
 enum BufferState {
@@ -11,6 +15,10 @@ enum BufferState {
 /// Buffer that stores some bytes before and after the current byte view
 pub struct Buffer {
     pub active_size: usize,
+    /// Absolute offset of index 0 in the input
+    pub position: usize,
+    /// Absolute offset of the first byte read into the buffer
+    start_position: usize,
     pub min_index: isize,
     pub max_index: isize,
     buffer: Vec<u8>,
@@ -24,12 +32,15 @@ impl Buffer {
     /// Create a new buffer with the given size
     ///
     /// The buffer will keep `size` previous bytes, `size` current bytes and `size` next bytes.
-    pub fn new(size: usize) -> Buffer {
+    /// `position` is the absolute offset of the first byte that will be read into the buffer.
+    pub fn new(size: usize, position: usize) -> Buffer {
         Buffer {
             buffer: vec![0; 3 * size],
             root_index: 0,
             size,
             active_size: size,
+            position,
+            start_position: position,
             min_index: 0,
             max_index: 0,
             state: BufferState::Uninitialised,
@@ -39,21 +50,46 @@ impl Buffer {
 
     /// Read bytes from `f` till the internal buffer is filled or EOF is reached
     pub fn read(&mut self, f: &mut impl std::io::Read) -> io::Result<usize> {
-        let next_buffer = self.mut_buffer();
+        let region = self.next_region();
         let mut read_bytes = 0;
         loop {
-            let n = f.read(&mut next_buffer[read_bytes..])?;
-            if n == 0 {
-                self.is_eof = true;
-                self.eof_reached(read_bytes);
-                break;
+            let n = f.read(&mut self.buffer[region.start + read_bytes..region.end])?;
+            if self.advance(&region, &mut read_bytes, n) {
+                return Ok(read_bytes);
             }
-            read_bytes += n;
-            if read_bytes == next_buffer.len() {
-                break;
+        }
+    }
+
+    /// Read bytes from `f` till the internal buffer is filled or EOF is reached
+    #[cfg(feature = "async")]
+    pub async fn read_async(
+        &mut self,
+        f: &mut (impl tokio::io::AsyncRead + Unpin),
+    ) -> io::Result<usize> {
+        use tokio::io::AsyncReadExt;
+        let region = self.next_region();
+        let mut read_bytes = 0;
+        loop {
+            let n = f
+                .read(&mut self.buffer[region.start + read_bytes..region.end])
+                .await?;
+            if self.advance(&region, &mut read_bytes, n) {
+                return Ok(read_bytes);
             }
         }
-        Ok(read_bytes)
+    }
+
+    /// Account for `n` bytes read into `region` after `read_bytes` bytes
+    ///
+    /// Returns `true` if the region is full or EOF has been reached, i.e. `n` is 0.
+    fn advance(&mut self, region: &Range<usize>, read_bytes: &mut usize, n: usize) -> bool {
+        if n == 0 {
+            self.is_eof = true;
+            self.eof_reached(*read_bytes);
+            return true;
+        }
+        *read_bytes += n;
+        *read_bytes == region.len()
     }
 
     /// Is the buffer in EOF state
@@ -61,13 +97,28 @@ impl Buffer {
         self.is_eof
     }
 
-    /// Returns the value at offset `i` if this is a valid index
-    pub fn at(&self, i: isize) -> Option<u8> {
-        if i < self.min_index || i >= self.max_index {
-            return None;
-        }
-        let actual_index = self.get_absolute_index(i);
-        Some(self.buffer[actual_index])
+    /// Is index 0 the first byte read into the buffer
+    pub fn is_start(&self) -> bool {
+        self.position == self.start_position
+    }
+
+    /// The current chunk, which can be searched by the search engines
+    pub fn haystack(&self) -> Haystack<'_> {
+        let len = (self.max_index - self.min_index) as usize;
+        let first = self.get_absolute_index(self.min_index);
+        // The view may cover the whole ring, in which case `first` equals the index of its end
+        let first_len = cmp::min(len, self.buffer.len() - first);
+        Haystack::from_parts(
+            (
+                &self.buffer[first..first + first_len],
+                &self.buffer[..len - first_len],
+            ),
+            self.min_index,
+            self.active_size,
+            self.position,
+            self.is_start(),
+            self.is_eof(),
+        )
     }
 
     /// Return the view `[first, last)` of the buffer
@@ -89,10 +140,10 @@ impl Buffer {
         }
     }
 
-    /// Returns a writeable buffer
+    /// Returns the writeable region of the buffer
     ///
-    /// The intended usage is to repeatedly call this function to get a mutable buffer and fill
-    /// the complete returned buffer with data.
+    /// The intended usage is to repeatedly call this function to get a region and fill it
+    /// completely with data.
     ///
     /// If the buffer cannot be filled comletely this should be signalled by calling `eof_reached()`
     /// with the bytes written in the last operation.
@@ -102,7 +153,7 @@ impl Buffer {
     /// Initially all buffers are empty.  The first call will fill CURRENT and (possibly) NEXT.
     /// All subsequent calls will drop PREVIOUS, move CURRENT TO PREVIOUS, move NEXT to CURRENT and
     /// return the NEXT buffer for filling.
-    fn mut_buffer(&mut self) -> &mut [u8] {
+    fn next_region(&mut self) -> Range<usize> {
         let begin;
         let end;
         match self.state {
@@ -115,6 +166,7 @@ impl Buffer {
                 self.state = BufferState::InitialisationPending;
             }
             BufferState::InitialisationPending | BufferState::Initialised => {
+                self.position += self.size;
                 self.root_index = (self.root_index + self.size) % (3 * self.size);
                 begin = (self.root_index + 2 * self.size) % (3 * self.size);
                 end = begin + self.size;
@@ -131,7 +183,7 @@ impl Buffer {
             }
         }
         self.active_size = self.size;
-        &mut self.buffer[begin..end]
+        begin..end
     }
 
     /// Signal that EOF has been reached and the last chunk has `remaining`  bytes.  No further
@@ -0,0 +1,117 @@
+use std::cmp;
+use std::io::Write;
+
+use binarygrep_core::{BgrepError, BufferMatch};
+
+use crate::buffer::Buffer;
+use crate::extract::{Extract, write_error};
+use crate::searcher::Searcher;
+
+/// Patterns that delimit the regions cut out of the input
+pub struct Carve {
+    pub start: Searcher,
+    pub end: Searcher,
+    /// Maximum number of bytes of a single carved region
+    pub max_size: usize,
+}
+
+/// Region that is currently being written
+struct Carving {
+    out: Box<dyn Write>,
+    /// Absolute offset up to which the region has been written
+    written: usize,
+    /// Absolute offset at which the region is cut off if no end pattern is found before
+    limit: usize,
+    /// Absolute offset from which on matches of the end pattern terminate the region
+    end_from: usize,
+}
+
+/// Carves the regions of a single input chunk by chunk
+///
+/// Each region starts with a match of the start pattern and ends with the next match of the end
+/// pattern, at `max_size` bytes or at the end of the input, whatever comes first.  Matches of the
+/// start pattern inside of a region do not start a new region.
+pub struct Carver<'a> {
+    carve: &'a Carve,
+    extract: &'a Extract,
+    filename: &'a str,
+    current: Option<Carving>,
+    /// Absolute offset from which on matches of the start pattern start a new region
+    resume: usize,
+}
+
+impl<'a> Carver<'a> {
+    pub fn new(
+        carve: &'a Carve,
+        extract: &'a Extract,
+        filename: &'a str,
+        position: usize,
+    ) -> Carver<'a> {
+        Carver {
+            carve,
+            extract,
+            filename,
+            current: None,
+            resume: position,
+        }
+    }
+
+    /// Carve the current chunk of `buf` and call `started` with each match of the start pattern
+    /// that began a new region
+    ///
+    /// The patterns are searched for one match at a time, so memory does not grow with the
+    /// number of their matches.
+    pub fn carve(
+        &mut self,
+        buf: &Buffer,
+        started: &mut dyn FnMut(BufferMatch),
+    ) -> Result<(), BgrepError> {
+        let position = buf.position;
+        let chunk_end = position + buf.active_size;
+        loop {
+            if self.current.is_none() {
+                let Some(m) = self.carve.start.first_match(buf, self.resume) else {
+                    break;
+                };
+                self.current = Some(Carving {
+                    out: self.extract.open(self.filename, position + m.start)?,
+                    written: position + m.start,
+                    limit: (position + m.start).saturating_add(self.carve.max_size),
+                    end_from: position + m.start + m.len,
+                });
+                started(m);
+            }
+            let Some(carving) = &mut self.current else {
+                break;
+            };
+            let end = self.carve.end.first_match(buf, carving.end_from);
+            let stop = match &end {
+                Some(m) => cmp::min(position + m.start + m.len, carving.limit),
+                None => cmp::min(chunk_end, carving.limit),
+            };
+            if let Some((buf_a, buf_b)) = buf.view(
+                (carving.written - position) as isize,
+                (stop - position) as isize,
+            ) {
+                carving.out.write_all(buf_a).map_err(write_error)?;
+                carving.out.write_all(buf_b).map_err(write_error)?;
+            }
+            carving.written = stop;
+            if end.is_none() && stop < carving.limit {
+                // The region continues in the next chunk
+                break;
+            }
+            self.resume = stop;
+            self.finish()?;
+        }
+        Ok(())
+    }
+
+    /// Close the region that is currently being written
+    pub fn finish(&mut self) -> Result<(), BgrepError> {
+        if let Some(mut carving) = self.current.take() {
+            carving.out.flush().map_err(write_error)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,71 @@
+use std::io::Read;
+
+use binarygrep_core::BgrepError;
+
+/// Compression formats recognized by the magic bytes at the start of the data
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Gzip,
+    Xz,
+    Bzip2,
+    Zstd,
+}
+
+impl Format {
+    /// Format of the data starting with `header`, `None` if it is not compressed
+    pub fn detect(header: &[u8]) -> Option<Format> {
+        if header.starts_with(&[0x1f, 0x8b]) {
+            Some(Format::Gzip)
+        } else if header.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+            Some(Format::Xz)
+        } else if header.starts_with(b"BZh") {
+            Some(Format::Bzip2)
+        } else if header.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Some(Format::Zstd)
+        } else {
+            None
+        }
+    }
+
+    /// Reader of the decompressed data of `reader`
+    pub fn decoder<'a>(self, reader: impl Read + 'a) -> Result<Box<dyn Read + 'a>, BgrepError> {
+        Ok(match self {
+            Format::Gzip => Box::new(flate2::read::MultiGzDecoder::new(reader)),
+            Format::Xz => Box::new(lzma_rust2::XzReader::new(reader, true)),
+            Format::Bzip2 => Box::new(bzip2::read::MultiBzDecoder::new(reader)),
+            Format::Zstd => Box::new(
+                ruzstd::decoding::StreamingDecoder::new(reader)
+                    .map_err(|err| BgrepError(format!("Invalid zstd data: {}", err)))?,
+            ),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+
+    #[test]
+    fn test_decoder() {
+        let data = std::fs::read("tests/testdata_783").unwrap();
+        for (extension, format) in [
+            ("gz", Format::Gzip),
+            ("xz", Format::Xz),
+            ("bz2", Format::Bzip2),
+            ("zst", Format::Zstd),
+        ] {
+            let compressed = std::fs::read(format!("tests/testdata_783.{}", extension)).unwrap();
+            assert_eq!(Format::detect(&compressed), Some(format));
+            let mut decompressed = vec![];
+            format
+                .decoder(io::Cursor::new(compressed))
+                .unwrap()
+                .read_to_end(&mut decompressed)
+                .unwrap();
+            assert_eq!(decompressed, data);
+        }
+        assert_eq!(Format::detect(&data), None);
+        assert_eq!(Format::detect(&[0x1f]), None);
+    }
+}
@@ -0,0 +1,97 @@
+use std::fs;
+use std::io;
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+
+use binarygrep_core::BgrepError;
+
+/// Destination for the raw bytes of matches
+pub enum Extract {
+    /// One file per match in the given directory
+    Directory(PathBuf),
+    /// All matches concatenated on standard output
+    Stdout,
+}
+
+impl Extract {
+    /// Extract to the directory `dir`, which is created if it does not exist
+    pub fn directory(dir: &str) -> Result<Extract, BgrepError> {
+        fs::create_dir_all(dir)
+            .map_err(|err| BgrepError(format!("Cannot create directory '{}': {}", dir, err)))?;
+        Ok(Extract::Directory(PathBuf::from(dir)))
+    }
+
+    /// Open the destination for bytes found at `address` in `file`
+    pub fn open(&self, file: &str, address: usize) -> Result<Box<dyn Write>, BgrepError> {
+        match self {
+            Extract::Directory(dir) => {
+                let path = dir.join(file_name(file, address));
+                let f = fs::File::create(&path).map_err(|err| {
+                    BgrepError(format!(
+                        "Cannot create file '{}': {}",
+                        path.to_string_lossy(),
+                        err
+                    ))
+                })?;
+                Ok(Box::new(io::BufWriter::new(f)))
+            }
+            Extract::Stdout => Ok(Box::new(io::stdout())),
+        }
+    }
+
+    /// Write the consecutive `parts` found at `address` in `file`
+    pub fn write(
+        &self,
+        file: &str,
+        address: usize,
+        parts: &[(&[u8], &[u8])],
+    ) -> Result<(), BgrepError> {
+        let mut out = self.open(file, address)?;
+        for (buf_a, buf_b) in parts {
+            out.write_all(buf_a).map_err(write_error)?;
+            out.write_all(buf_b).map_err(write_error)?;
+        }
+        out.flush().map_err(write_error)
+    }
+}
+
+pub fn write_error(err: io::Error) -> BgrepError {
+    BgrepError(format!("Error while writing: {}", err))
+}
+
+/// Name of the file storing bytes found at `address` in `file`, e.g. `dir_dump.bin_0001f60d.bin`
+///
+/// Directories of `file` are kept in the name so that equally named files from different
+/// directories do not overwrite each other when searching recursively.
+fn file_name(file: &str, address: usize) -> String {
+    let name = if file == "-" {
+        String::from("stdin")
+    } else {
+        Path::new(file)
+            .components()
+            .filter_map(|component| match component {
+                Component::Normal(name) => Some(name.to_string_lossy()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("_")
+    };
+    format!("{}_{:08x}.bin", name, address)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_name() {
+        assert_eq!(file_name("dump.bin", 0x1f60d), "dump.bin_0001f60d.bin");
+        assert_eq!(
+            file_name("tests/subdir/testdata_1200", 0x2d1),
+            "tests_subdir_testdata_1200_000002d1.bin"
+        );
+        assert_eq!(file_name("/dev/sda", 0), "dev_sda_00000000.bin");
+        assert_eq!(file_name("../a/./b", 16), "a_b_00000010.bin");
+        assert_eq!(file_name("-", 0x123456789), "stdin_123456789.bin");
+    }
+}
@@ -1,27 +1,59 @@
 use std::cmp;
 use std::fs;
 use std::io;
+use std::io::{Read, Seek, SeekFrom};
+use std::ops::ControlFlow;
 use std::path::Path;
 
-use clap::Parser;
+use binarygrep_core::Decode;
+use clap::builder::{PossibleValuesParser, TypedValueParser};
+use clap::parser::ValueSource;
+use clap::{CommandFactory, FromArgMatches, Parser};
 use colored::Colorize;
 
 mod buffer;
 use crate::buffer::Buffer;
 
-mod search;
-use search::Search;
+#[cfg(feature = "async")]
+mod asyncsearcher;
 
-mod bmsearch;
-use bmsearch::BoyerMooreSearch;
+mod searcher;
+pub use searcher::{Context, Match, Matches, Searcher, SearcherBuilder, Sink};
 
-mod extendedsearch;
-use extendedsearch::ExtendedSearch;
+pub use binarygrep_core::BgrepError;
 
-mod bgreperror;
-pub use bgreperror::BgrepError;
+mod range;
+use range::{Position, Range};
 
-const BUFFER_SIZE: usize = 4 * 1024 * 1024;
+mod extract;
+use extract::Extract;
+
+mod carve;
+use carve::{Carve, Carver};
+
+mod replace;
+use replace::{Edit, Replace, Replacement, Target};
+
+mod patch;
+use patch::PatchFormat;
+
+mod archive;
+use archive::Kind;
+
+mod replay;
+use replay::Replay;
+
+mod decompress;
+use decompress::Format;
+
+#[cfg(target_os = "linux")]
+mod process;
+
+mod walk;
+use walk::{Devices, Dir, Follow, Sort, Walk};
+
+/// Number of bytes needed to recognize the formats of compressed data and archives
+const HEADER_LEN: u64 = 262;
 
 #[derive(Parser)]
 #[command(
@@ -32,19 +64,97 @@ Extended patterns consist of:
 - The wildcard character matching an arbitrary single byte: .
 - Character sets: [02,ac,77] (either 0x02, 0xac or 0x77)
 - Quantifiers: 03{5} (five times 0x03), 03{2,5} (two till five times 0x03)
+- Groups: (.{4}) (captures four arbitrary bytes)
+- Back-references: (.{4}) 00 \1 (the bytes captured by the first group, \1 to \9)
+- Fields: (len:u16le) .{len} (a length and that many bytes, counts are at most 1048576)
+- Anchors: ^7f454c46 (at the start of the searched range), 0000$ (at its end)
 - Spaces since they are always ignored
 Example: 00{10} .{1,3} [00,FF]{2,3} AA BB
+
+The bytes captured by the groups of an extended pattern are printed along each match.
+Example: -x '4d5a .{58} (.{4})' --decode u32le
+
+With --replace, \N inserts the bytes captured by the N-th group of an extended pattern.
+Without groups, it inserts the bytes matched by the N-th wildcard or character set.
+Consecutive equal wildcards or sets count as one.
+Example: --replace '9090 \1' -x 'eb .'
 "#
 )]
 struct Cli {
     /// Pattern as hexadecimal string
-    pattern: String,
+    #[arg(required_unless_present = "carve")]
+    pattern: Option<String>,
     /// Search for PATTERN in each file. "-" is standard input.
     #[arg(default_values_t = ["-".to_string()])]
     file: Vec<String>,
-    /// Search in all files recursively, symbolic links are followed
+    /// Search in all files recursively, symbolic links are only followed if given on the command line
     #[arg(short = 'r', long)]
     recursive: bool,
+    /// Search in all files recursively, following all symbolic links
+    #[arg(short = 'R', long, conflicts_with = "no_follow")]
+    dereference_recursive: bool,
+    /// Do not follow symbolic links given on the command line when searching recursively
+    #[arg(long, requires = "recursive")]
+    no_follow: bool,
+    /// Do not descend into directories on other file systems when searching recursively
+    #[arg(long)]
+    one_file_system: bool,
+    /// Read or skip devices, FIFOs and sockets, those found by a recursive search are only read
+    /// if they are devices [default: read those given on the command line]
+    #[arg(short = 'D', long, value_name = "ACTION", value_enum)]
+    devices: Option<Devices>,
+    /// Order in which the entries of directories are searched, smallest or least recently
+    /// modified first for size and mtime
+    #[arg(long, value_name = "KEY", value_enum, default_value_t = Sort::Path)]
+    sort: Sort,
+    /// Only search files whose name matches <GLOB> when searching recursively
+    #[arg(long, value_name = "GLOB")]
+    include: Vec<String>,
+    /// Skip files whose name matches <GLOB> when searching recursively
+    #[arg(long, value_name = "GLOB")]
+    exclude: Vec<String>,
+    /// Skip directories whose name matches <GLOB> when searching recursively
+    #[arg(long, value_name = "GLOB")]
+    exclude_dir: Vec<String>,
+    /// Descend at most <N> directory levels below the given directories (0 only searches the
+    /// files directly in them)
+    #[arg(long, value_name = "N")]
+    max_depth: Option<usize>,
+    /// Skip files and directories whose name starts with a dot when searching recursively
+    #[arg(long)]
+    skip_hidden: bool,
+    /// Do not skip files and directories matched by .gitignore, .ignore and .bgrepignore files
+    #[arg(long)]
+    no_ignore: bool,
+    /// Skip files smaller than <N> bytes (k/M/G suffixes)
+    #[arg(long, value_name = "N", value_parser = range::parse_size)]
+    min_size: Option<u64>,
+    /// Skip files larger than <N> bytes (k/M/G suffixes)
+    #[arg(long, value_name = "N", value_parser = range::parse_size)]
+    max_size: Option<u64>,
+    /// Search the decompressed data of gzip, xz, bzip2 and zstd compressed inputs, offsets refer to
+    /// the decompressed data
+    #[arg(short = 'z', long, conflicts_with = "replace")]
+    decompress: bool,
+    /// Search each member of zip, tar and cpio archives as FILE!MEMBER
+    #[arg(long, conflicts_with = "replace")]
+    archives: bool,
+    /// Search members of archives nested in at most <N> archives
+    #[arg(long, value_name = "N", default_value_t = 3, requires = "archives",
+          value_parser = clap::value_parser!(u64).range(1..))]
+    archive_depth: u64,
+    /// Search the memory of the running process <PID> instead of files, offsets are virtual
+    /// addresses
+    #[arg(long, value_name = "PID", conflicts_with_all = [
+        "file", "recursive", "dereference_recursive", "decompress", "archives", "skip", "length",
+        "end", "replace"])]
+    pid: Option<u32>,
+    /// Only search mappings with the permissions <PERMS> such as r-x, "?" matches any permission
+    #[arg(long, value_name = "PERMS", requires = "pid")]
+    maps: Option<String>,
+    /// Only search mappings whose file or pseudo name such as [heap] contains <NAME>
+    #[arg(long, value_name = "NAME", requires = "pid")]
+    map_name: Option<String>,
     /// Enable extended search patterns (see below for syntax)
     #[arg(short = 'x', long)]
     extended: bool,
@@ -69,6 +179,76 @@ struct Cli {
     /// Suppress 0-based offset of matched bytes in output
     #[arg(long, default_value_t = false)]
     no_offset: bool,
+    /// Print the bytes captured by groups decoded as <TYPE> instead of hexadecimal
+    #[arg(long, value_name = "TYPE", value_parser = decode_parser())]
+    decode: Option<Decode>,
+    /// Skip the first <N> bytes of each input (k/M/G suffixes, negative counts from the end)
+    #[arg(long, visible_alias = "start", value_name = "N", allow_hyphen_values = true,
+          value_parser = range::parse_position)]
+    skip: Option<Position>,
+    /// Search at most <N> bytes of each input (negative stops <N> bytes before the end)
+    #[arg(long, value_name = "N", allow_hyphen_values = true, conflicts_with = "end",
+          value_parser = range::parse_position)]
+    length: Option<Position>,
+    /// Stop searching at offset <N> of each input (negative counts from the end)
+    #[arg(long, value_name = "N", allow_hyphen_values = true,
+          value_parser = range::parse_position)]
+    end: Option<Position>,
+    /// Only report matches whose offset is a multiple of <N>
+    #[arg(long, value_name = "N", default_value_t = 1,
+          value_parser = clap::value_parser!(u64).range(1..))]
+    align: u64,
+    /// Only report matches whose offset minus <K> is a multiple of the --align value
+    #[arg(long, value_name = "K", default_value_t = 0, requires = "align")]
+    align_offset: usize,
+    /// Do not report matches that overlap a previously reported match
+    #[arg(long)]
+    no_overlap: bool,
+    /// Write the bytes of each match (including context) to <DIR>/<FILE>_<OFFSET>.bin
+    #[arg(long, value_name = "DIR", conflicts_with = "extract_stdout")]
+    extract: Option<String>,
+    /// Write the bytes of all matches (including context) to standard output
+    #[arg(long)]
+    extract_stdout: bool,
+    /// Extract everything from a match of START through the next match of END (replaces PATTERN)
+    #[arg(long, num_args = 2, value_names = ["START", "END"])]
+    carve: Option<Vec<String>>,
+    /// Stop carving after <N> bytes if no END has been found (k/M/G suffixes)
+    #[arg(long, value_name = "N", requires = "carve", value_parser = range::parse_size)]
+    carve_max_size: Option<u64>,
+    /// Replace each match by <HEX>, \N inserts the N-th capture of an extended pattern
+    #[arg(long, value_name = "HEX", conflicts_with_all = ["carve", "extract", "extract_stdout"])]
+    replace: Option<String>,
+    /// Write the result of --replace to <FILE> ("-" is standard output)
+    #[arg(short = 'o', long, value_name = "FILE", requires = "replace",
+          conflicts_with_all = ["in_place", "emit_patch", "dry_run"])]
+    output: Option<String>,
+    /// Write the result of --replace to the input files
+    #[arg(long, requires = "replace", conflicts_with_all = ["emit_patch", "dry_run"])]
+    in_place: bool,
+    /// Keep the original of files modified in place with this suffix, empty for no backup
+    /// (existing backups are not overwritten and skipped when searching recursively)
+    #[arg(
+        long,
+        value_name = "SUFFIX",
+        default_value = ".bak",
+        requires = "in_place"
+    )]
+    backup_suffix: String,
+    /// Write an IPS or BPS patch (chosen by the extension) with the result of --replace to <FILE>
+    #[arg(
+        long,
+        value_name = "FILE",
+        requires = "replace",
+        conflicts_with = "dry_run"
+    )]
+    emit_patch: Option<String>,
+    /// Allow replacements whose length differs from the length of the match
+    #[arg(long, requires = "replace")]
+    allow_resize: bool,
+    /// Print the bytes --replace would change instead of writing the result
+    #[arg(long, requires = "replace")]
+    dry_run: bool,
 }
 
 fn encode_hex((buf_a, buf_b): (&[u8], &[u8])) -> String {
@@ -96,43 +276,212 @@ fn ascii_interpretation((buf_a, buf_b): (&[u8], &[u8])) -> String {
     ascii
 }
 
-struct Bgrep<T: Search> {
+struct Bgrep {
     recursive: bool,
+    walk: Walk,
+    decompress: bool,
+    /// Number of nested archives whose members are searched, `None` if archives are not unpacked
+    archive_depth: Option<usize>,
     after: usize,
     before: usize,
     with_filename: bool,
     no_ascii: bool,
     no_offset: bool,
-    search: T,
+    /// Print the captures of the pattern along each match
+    print_captures: bool,
+    decode: Option<Decode>,
+    range: Range,
+    extract: Option<Extract>,
+    search: Searcher,
+    carve: Option<Carve>,
+    replace: Option<Replace>,
 }
 
-impl<T: Search> Bgrep<T> {
-    fn new(cli: &Cli) -> Result<Bgrep<T>, BgrepError> {
-        let multiple_files = cli.file.len() > 1 || cli.recursive;
+impl Bgrep {
+    fn new(cli: &Cli) -> Result<Bgrep, BgrepError> {
+        let recursive = cli.recursive || cli.dereference_recursive;
+        let multiple_files = cli.file.len() > 1 || recursive || cli.archives || cli.pid.is_some();
+        let builder = Searcher::builder()
+            .extended(cli.extended)
+            .align(cli.align as usize)
+            .align_offset(cli.align_offset)
+            .context(cmp::max(cmp::max(cli.after, cli.before), cli.context))
+            // Replacements must not overlap
+            .no_overlap(cli.no_overlap || cli.replace.is_some());
+        let carve = match &cli.carve {
+            Some(_) if cli.extract.is_none() && !cli.extract_stdout => {
+                return Err(BgrepError(String::from(
+                    "--carve requires --extract or --extract-stdout",
+                )));
+            }
+            Some(patterns) => Some(Carve {
+                start: builder.clone().pattern(&patterns[0]).build()?,
+                end: Searcher::builder()
+                    .extended(cli.extended)
+                    .pattern(&patterns[1])
+                    .build()?,
+                max_size: cli.carve_max_size.map_or(usize::MAX, |size| size as usize),
+            }),
+            None => None,
+        };
+        let replace = match &cli.replace {
+            Some(replacement) => {
+                let target = if let Some(output) = &cli.output {
+                    if multiple_files {
+                        return Err(BgrepError(String::from(
+                            "--output requires a single input file",
+                        )));
+                    }
+                    Target::Output(output.clone())
+                } else if cli.in_place {
+                    Target::InPlace(cli.backup_suffix.clone())
+                } else if let Some(patch) = &cli.emit_patch {
+                    if multiple_files {
+                        return Err(BgrepError(String::from(
+                            "--emit-patch requires a single input file",
+                        )));
+                    }
+                    Target::Patch(PatchFormat::from_path(patch)?, patch.clone())
+                } else if cli.dry_run {
+                    Target::DryRun
+                } else {
+                    return Err(BgrepError(String::from(
+                        "--replace requires --output, --in-place, --emit-patch or --dry-run",
+                    )));
+                };
+                Some(Replace {
+                    replacement: Replacement::new(replacement)?,
+                    target,
+                    allow_resize: cli.allow_resize,
+                })
+            }
+            None => None,
+        };
+        let search = builder
+            .pattern(cli.pattern.as_deref().unwrap_or_default())
+            .build()?;
+        if let Some(replace) = &replace
+            && replace.replacement.max_capture() > search.capture_count()
+        {
+            return Err(BgrepError(format!(
+                "Replacement refers to capture \\{} but the pattern has {} captures",
+                replace.replacement.max_capture(),
+                search.capture_count()
+            )));
+        }
         Ok(Bgrep {
-            recursive: cli.recursive,
+            recursive,
+            walk: Walk {
+                include: walk::glob_set(&cli.include)?,
+                exclude: walk::glob_set(&cli.exclude)?,
+                exclude_dir: walk::glob_set(&cli.exclude_dir)?,
+                max_depth: cli.max_depth,
+                min_size: cli.min_size,
+                max_size: cli.max_size,
+                skip_hidden: cli.skip_hidden,
+                ignore_files: !cli.no_ignore,
+                follow: if cli.dereference_recursive {
+                    Follow::All
+                } else if cli.no_follow {
+                    Follow::Never
+                } else {
+                    Follow::CommandLine
+                },
+                one_file_system: cli.one_file_system,
+                devices: cli.devices,
+                sort: cli.sort,
+                ..Default::default()
+            },
+            decompress: cli.decompress,
+            archive_depth: cli.archives.then_some(cli.archive_depth as usize),
             after: cmp::max(cli.after, cli.context),
             before: cmp::max(cli.before, cli.context),
             with_filename: (multiple_files && !cli.no_filename)
                 || (!multiple_files && cli.with_filename),
             no_ascii: cli.no_ascii,
             no_offset: cli.no_offset,
-            search: T::new(&cli.pattern)?,
+            print_captures: match &carve {
+                Some(carve) => carve.start.has_groups(),
+                None => search.has_groups(),
+            },
+            decode: cli.decode,
+            range: Range {
+                skip: cli.skip,
+                length: cli.length,
+                end: cli.end,
+            },
+            extract: if let Some(dir) = &cli.extract {
+                Some(Extract::directory(dir)?)
+            } else if cli.extract_stdout {
+                Some(Extract::Stdout)
+            } else {
+                None
+            },
+            search,
+            carve,
+            replace,
         })
     }
 
     fn grep(&self, file: &str) -> Result<(), BgrepError> {
         if file == "-" {
             let mut f = io::stdin();
-            self.grep_fd(file, &mut f)?;
+            if let Some(replace) = &self.replace {
+                return self.replace_stdin(replace, &mut f);
+            }
+            if self.decompress || self.archive_depth.is_some() {
+                let header = read_header(&mut f)?;
+                self.grep_data(file, header, &mut f, 0)?;
+            } else {
+                self.grep_stream(file, f)?;
+            }
         } else {
             let path = Path::new(&file);
-            self.grep_path(path)?;
+            if !self.recursive || self.walk.follows(path, true) {
+                self.grep_path(path, None)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Search the readable memory regions of the process `pid`
+    ///
+    /// Regions are labelled with their name and permissions instead of a filename.
+    #[cfg(target_os = "linux")]
+    fn grep_process(
+        &self,
+        pid: u32,
+        perms: Option<&str>,
+        name: Option<&str>,
+    ) -> Result<(), BgrepError> {
+        let mem = process::open_memory(pid)?;
+        for mapping in process::mappings(pid)? {
+            if mapping.is_readable()
+                && perms.is_none_or(|perms| mapping.has_perms(perms))
+                && name.is_none_or(|name| mapping.path.contains(name))
+            {
+                self.grep_fd(
+                    &mapping.label(),
+                    &mut process::Region::new(&mem, &mapping),
+                    mapping.start,
+                )?;
+            }
         }
         Ok(())
     }
 
-    fn grep_path(&self, path: &Path) -> Result<(), BgrepError> {
+    #[cfg(not(target_os = "linux"))]
+    fn grep_process(
+        &self,
+        _pid: u32,
+        _perms: Option<&str>,
+        _name: Option<&str>,
+    ) -> Result<(), BgrepError> {
+        Err(BgrepError(String::from("--pid is only supported on Linux")))
+    }
+
+    /// Search `path`, which is an entry of `parent` or given on the command line
+    fn grep_path(&self, path: &Path, parent: Option<&Dir>) -> Result<(), BgrepError> {
         if path.is_dir() {
             if !self.recursive {
                 return Err(BgrepError(format!(
@@ -140,61 +489,356 @@ impl<T: Search> Bgrep<T> {
                     &path.to_str().unwrap()
                 )));
             }
-            for entry in fs::read_dir(path)
+            let Some(dir) = self.walk.enter(path, parent)? else {
+                return Ok(());
+            };
+            let mut entries = fs::read_dir(path)
                 .map_err(|err| BgrepError(format!("Error while reading directory: {}", err)))?
-            {
-                let entry_path = entry
-                    .map_err(|err| BgrepError(format!("Error accessing directory entry: {}", err)))?
-                    .path();
-                self.grep_path(&entry_path)?;
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|err| BgrepError(format!("Error accessing directory entry: {}", err)))?;
+            self.walk.sort(&mut entries);
+            for entry_path in entries {
+                if self.walk.follows(&entry_path, false)
+                    && self.walk.visit(&entry_path, entry_path.is_dir(), &dir)
+                {
+                    self.grep_path(&entry_path, Some(&dir))?;
+                }
+            }
+        } else if !self.walk.accepts(path, parent.is_none())? {
+            return Ok(());
+        } else if let Some(replace) = &self.replace {
+            if parent.is_none() || !replace.is_backup(path) {
+                self.replace_file(replace, path)?;
             }
+        } else if self.decompress || self.archive_depth.is_some() {
+            let filename = path.to_str().unwrap();
+            let mut f = open(path)?;
+            let header = read_header(&mut f)?;
+            if !self.unpacks(&header, 0) && f.rewind().is_ok() {
+                // Searched like without --decompress and --archives if possible
+                self.grep_file(path, f)?;
+            } else if Kind::detect(&header) == Some(Kind::Zip) && f.rewind().is_ok() {
+                // Read directly instead of into memory
+                let mut visited = false;
+                let result = archive::zip_members(filename, &mut f, &mut |name, member| {
+                    visited = true;
+                    self.grep_member(filename, name, member, 1)
+                });
+                match result {
+                    // Only looks like a zip archive, e.g. firmware that starts with "PK\x03\x04"
+                    Err(_) if !visited && f.rewind().is_ok() => self.grep_file(path, f)?,
+                    result => result?,
+                }
+            } else {
+                self.grep_data(filename, header, &mut f, 0)?;
+            }
+        } else {
+            self.grep_file(path, open(path)?)?;
+        }
+        Ok(())
+    }
+
+    /// Search the file `f` opened from `path` within the searched range
+    fn grep_file(&self, path: &Path, f: fs::File) -> Result<(), BgrepError> {
+        let (f, start, end) = self.seek_range(path, f)?;
+        self.grep_fd(
+            path.to_str().unwrap(),
+            &mut f.take(limit(start, end)),
+            start,
+        )
+    }
+
+    /// Whether data starting with `header`, which is nested in `depth` archives, is unpacked
+    fn unpacks(&self, header: &[u8], depth: usize) -> bool {
+        (self.decompress && Format::detect(header).is_some())
+            || (self
+                .archive_depth
+                .is_some_and(|max_depth| depth < max_depth)
+                && Kind::detect(header).is_some())
+    }
+
+    /// Search the data of `filename`, which is nested in `depth` archives
+    ///
+    /// `header` are the first bytes of the data and `f` the rest.  Compressed data and archives
+    /// are unpacked if enabled, other data is searched as it is.
+    fn grep_data(
+        &self,
+        filename: &str,
+        header: Vec<u8>,
+        f: &mut dyn Read,
+        depth: usize,
+    ) -> Result<(), BgrepError> {
+        if !self.unpacks(&header, depth) {
+            return self.grep_stream(filename, io::Cursor::new(header).chain(f));
+        }
+        if self.decompress
+            && let Some(format) = Format::detect(&header)
+        {
+            let mut replay = Replay::new(io::Cursor::new(header).chain(f));
+            let recording = replay.recording();
+            let result = format.decoder(&mut replay).and_then(|mut decoder| {
+                let header = read_header(&mut decoder)?;
+                recording.set(false);
+                self.grep_data(filename, header, &mut decoder, depth)
+            });
+            return match result {
+                // Only looks compressed, e.g. data that starts with "BZh"
+                Err(_) if recording.get() => self.grep_stream(filename, replay.replay().unwrap()),
+                result => result,
+            };
+        }
+        let kind = Kind::detect(&header).unwrap();
+        let mut replay = Replay::new(io::Cursor::new(header).chain(f));
+        let recording = replay.recording();
+        let result = kind.for_each_member(filename, &mut replay, &mut |name, member| {
+            recording.set(false);
+            self.grep_member(filename, name, member, depth + 1)
+        });
+        match result {
+            // Only looks like an archive if it fails before the first member
+            Err(_) if recording.get() => self.grep_stream(filename, replay.replay().unwrap()),
+            result => result,
+        }
+    }
+
+    /// Search the member `name` of `archive`, which is nested in `depth` archives itself
+    fn grep_member(
+        &self,
+        archive: &str,
+        name: &str,
+        member: &mut dyn Read,
+        depth: usize,
+    ) -> Result<(), BgrepError> {
+        let header = read_header(member)?;
+        self.grep_data(&format!("{}!{}", archive, name), header, member, depth)
+    }
+
+    /// Search `f`, which cannot seek and whose size is unknown, within the searched range
+    fn grep_stream(&self, filename: &str, mut f: impl Read) -> Result<(), BgrepError> {
+        let (start, end) = self.range.resolve(None)?;
+        io::copy(&mut f.by_ref().take(start), &mut io::sink())
+            .map_err(|err| BgrepError(format!("Error while reading: {}", err)))?;
+        self.grep_fd(filename, &mut f.take(limit(start, end)), start)
+    }
+
+    /// Open the file `path` and seek to the start of the searched range
+    ///
+    /// Returns the file and the searched range `[start, end)`.
+    fn open_file(&self, path: &Path) -> Result<(fs::File, u64, Option<u64>), BgrepError> {
+        self.seek_range(path, open(path)?)
+    }
+
+    /// Seek to the start of the searched range of the file `f` opened from `path`
+    ///
+    /// Returns the file and the searched range `[start, end)`.
+    fn seek_range(
+        &self,
+        path: &Path,
+        mut f: fs::File,
+    ) -> Result<(fs::File, u64, Option<u64>), BgrepError> {
+        let size = if self.range.needs_size() {
+            let size = walk::file_size(&mut f).map_err(|err| {
+                BgrepError(format!(
+                    "Cannot read metadata of '{}': {}",
+                    &path.to_str().unwrap(),
+                    err
+                ))
+            })?;
+            Some(size)
         } else {
-            let mut f = std::fs::File::open(path).map_err(|err| {
+            None
+        };
+        let (start, end) = self.range.resolve(size)?;
+        if start > 0 {
+            f.seek(SeekFrom::Start(start)).map_err(|err| {
                 BgrepError(format!(
-                    "Cannot open file '{}': {}",
+                    "Cannot seek in file '{}': {}",
                     &path.to_str().unwrap(),
                     err
                 ))
             })?;
-            self.grep_fd(path.to_str().unwrap(), &mut f)?;
         }
-        Ok(())
+        Ok((f, start, end))
+    }
+
+    fn grep_fd(
+        &self,
+        filename: &str,
+        f: &mut impl std::io::Read,
+        start: u64,
+    ) -> Result<(), BgrepError> {
+        if let (Some(carve), Some(extract)) = (&self.carve, &self.extract) {
+            let mut carver = Carver::new(carve, extract, filename, start as usize);
+            return self.carve_fd(carve, &mut carver, f, start, filename);
+        }
+        // The sink cannot return errors, the first one stops the search
+        let mut result = Ok(());
+        self.search
+            .search_fd(f, start as usize, &mut |m: &Match, ctx: &Context| {
+                if let Some(extract) = &self.extract {
+                    let before = ctx.before(self.before);
+                    let after = ctx.after(self.after);
+                    let parts: [(&[u8], &[u8]); 3] =
+                        [(&before, &[]), (&m.bytes, &[]), (&after, &[])];
+                    if let Err(err) = extract.write(filename, m.offset, &parts) {
+                        result = Err(err);
+                        return ControlFlow::Break(());
+                    }
+                }
+                if !matches!(self.extract, Some(Extract::Stdout)) {
+                    self.print_match(m, ctx, filename);
+                }
+                ControlFlow::Continue(())
+            })?;
+        result
     }
 
-    fn grep_fd(&self, filename: &str, f: &mut impl std::io::Read) -> Result<(), BgrepError> {
-        let buffer_size = cmp::max(
-            BUFFER_SIZE,
-            self.search.max_pattern_len() + cmp::max(self.after, self.before),
+    fn carve_fd(
+        &self,
+        carve: &Carve,
+        carver: &mut Carver,
+        f: &mut impl std::io::Read,
+        start: u64,
+        filename: &str,
+    ) -> Result<(), BgrepError> {
+        let max_pattern_len = cmp::max(carve.start.max_pattern_len(), carve.end.max_pattern_len());
+        let mut buffer = Buffer::new(
+            searcher::buffer_size(max_pattern_len, self.context_size()),
+            start as usize,
         );
-        let mut buffer = Buffer::new(buffer_size);
-        let mut grep_ctr = 0;
         loop {
             buffer
                 .read(f)
                 .map_err(|err| BgrepError(format!("Error while reading: {}", err)))?;
-            self.grep_buffer(&buffer, grep_ctr, filename);
-            grep_ctr += buffer.active_size;
+            carver.carve(&buffer, &mut |m| {
+                if !matches!(self.extract, Some(Extract::Stdout)) {
+                    let ctx = Context::new(&buffer, &m, self.context_size());
+                    if let Some(m) = Match::new(&buffer, m) {
+                        self.print_match(&m, &ctx, filename);
+                    }
+                }
+            })?;
             if buffer.is_eof() {
                 break;
             }
         }
-        Ok(())
+        carver.finish()
     }
 
-    fn grep_buffer(&self, buf: &Buffer, offset: usize, filename: &str) {
-        let matches = self.search.search(buf, 0);
-        for (i, match_len) in matches {
-            let res_start = i as isize;
-            let res_end = (i + match_len) as isize;
-            let before_start = cmp::max(i as isize - self.before as isize, buf.min_index);
-            let after_end = cmp::min((i + match_len + self.after) as isize, buf.max_index);
-            if let (Some(before), Some(result), Some(after)) = (
-                buf.view(before_start, res_start),
-                buf.view(res_start, res_end),
-                buf.view(res_end, after_end),
-            ) {
-                self.print_result(filename, offset + i, before, result, after);
-            }
+    fn replace_file(&self, replace: &Replace, path: &Path) -> Result<(), BgrepError> {
+        let filename = path.to_str().unwrap();
+        let (mut f, start, end) = self.open_file(path)?;
+        let edits = self.collect_edits(replace, &mut f.by_ref().take(limit(start, end)), start)?;
+        if let Target::DryRun = replace.target {
+            self.print_edits(filename, &edits);
+            return Ok(());
+        }
+        f.rewind()
+            .map_err(|err| BgrepError(format!("Cannot seek in file '{}': {}", filename, err)))?;
+        replace.write(filename, &mut io::BufReader::new(f), &edits)
+    }
+
+    fn replace_stdin(&self, replace: &Replace, f: &mut impl Read) -> Result<(), BgrepError> {
+        if let Target::InPlace(_) = replace.target {
+            return Err(BgrepError(String::from(
+                "Cannot modify standard input in place",
+            )));
+        }
+        // The input is needed twice, for searching and for writing the result
+        let mut data = vec![];
+        f.read_to_end(&mut data)
+            .map_err(|err| BgrepError(format!("Error while reading: {}", err)))?;
+        let (start, end) = self.range.resolve(Some(data.len() as u64))?;
+        let searched = &data[start as usize..end.map_or(data.len(), |end| end as usize)];
+        let edits = self.collect_edits(replace, &mut &searched[..], start)?;
+        if let Target::DryRun = replace.target {
+            self.print_edits("-", &edits);
+            return Ok(());
+        }
+        replace.write("-", &mut &data[..], &edits)
+    }
+
+    fn collect_edits(
+        &self,
+        replace: &Replace,
+        f: &mut impl std::io::Read,
+        start: u64,
+    ) -> Result<Vec<Edit>, BgrepError> {
+        let mut edits = vec![];
+        let mut result = Ok(());
+        self.search.search_fd(
+            f,
+            start as usize,
+            &mut |m: &Match, _: &Context| match replace.edit(m.offset, m.bytes.clone(), &m.captures)
+            {
+                Ok(edit) => {
+                    edits.push(edit);
+                    ControlFlow::Continue(())
+                }
+                Err(err) => {
+                    result = Err(err);
+                    ControlFlow::Break(())
+                }
+            },
+        )?;
+        result.map(|_| edits)
+    }
+
+    /// Number of bytes printed before or after a match
+    fn context_size(&self) -> usize {
+        cmp::max(self.after, self.before)
+    }
+
+    fn print_match(&self, m: &Match, ctx: &Context, filename: &str) {
+        let captures = if self.print_captures {
+            self.format_captures(m)
+        } else {
+            String::new()
+        };
+        self.print_result(
+            filename,
+            m.offset,
+            (&ctx.before(self.before), &[]),
+            (&m.bytes, &[]),
+            (&ctx.after(self.after), &[]),
+            &captures,
+        );
+    }
+
+    /// Returns the captures of `m` as `\1=<value> \2=<value> ...`
+    fn format_captures(&self, m: &Match) -> String {
+        let mut captures = vec![];
+        for n in 1..=m.captures.len() {
+            let Some(bytes) = m.capture(n) else {
+                continue;
+            };
+            let value = self
+                .decode
+                .and_then(|decode| decode.decode(bytes))
+                .unwrap_or_else(|| encode_hex((bytes, &[])));
+            captures.push(format!("\\{}={}", n, value));
+        }
+        captures.join(" ")
+    }
+
+    fn print_edits(&self, file: &str, edits: &[Edit]) {
+        let filename = if self.with_filename { file } else { "" };
+        for edit in edits {
+            let offset = if self.no_offset {
+                String::new()
+            } else {
+                format!("{:08x}", edit.offset)
+            };
+            println!(
+                "{}{}{}{}{} -> {}",
+                filename.cyan(),
+                if filename.is_empty() { "" } else { " " },
+                offset.bold(),
+                if offset.is_empty() { "" } else { ": " },
+                encode_hex((&edit.old, &[])).magenta(),
+                encode_hex((&edit.new, &[])).green()
+            );
         }
     }
 
@@ -205,6 +849,7 @@ impl<T: Search> Bgrep<T> {
         before: (&[u8], &[u8]),
         result: (&[u8], &[u8]),
         after: (&[u8], &[u8]),
+        captures: &str,
     ) {
         let filename = if self.with_filename { file } else { "" };
         let offset = if self.no_offset {
@@ -231,7 +876,7 @@ impl<T: Search> Bgrep<T> {
             ascii_interpretation(after)
         };
         println!(
-            "{}{}{}{}{}{}{}{}{}{}{}",
+            "{}{}{}{}{}{}{}{}{}{}{}{}{}",
             filename.cyan(),
             if filename.is_empty() { "" } else { " " },
             offset.bold(),
@@ -242,22 +887,62 @@ impl<T: Search> Bgrep<T> {
             if self.no_ascii { "" } else { "  " },
             ascii_before,
             ascii_result.magenta(),
-            ascii_after
+            ascii_after,
+            if captures.is_empty() { "" } else { "  " },
+            captures.yellow()
         );
     }
 }
 
-pub fn run() -> Result<(), BgrepError> {
-    let cli = Cli::parse();
-    if cli.extended {
-        run2::<ExtendedSearch>(&cli)
-    } else {
-        run2::<BoyerMooreSearch>(&cli)
-    }
+/// Parser of the names of the types of `--decode`, which are listed as possible values
+fn decode_parser() -> impl TypedValueParser<Value = Decode> {
+    PossibleValuesParser::new(Decode::ALL.map(|decode| decode.name()))
+        .map(|name| Decode::from_name(&name).unwrap())
+}
+
+/// Read the first bytes of `f`, which identify compressed data and archives
+///
+/// Returns fewer bytes only if `f` ends before.
+fn read_header(f: &mut dyn Read) -> Result<Vec<u8>, BgrepError> {
+    let mut header = Vec::with_capacity(HEADER_LEN as usize);
+    f.take(HEADER_LEN)
+        .read_to_end(&mut header)
+        .map_err(|err| BgrepError(format!("Error while reading: {}", err)))?;
+    Ok(header)
+}
+
+/// Open the file `path` for reading
+fn open(path: &Path) -> Result<fs::File, BgrepError> {
+    fs::File::open(path).map_err(|err| {
+        BgrepError(format!(
+            "Cannot open file '{}': {}",
+            &path.to_str().unwrap(),
+            err
+        ))
+    })
+}
+
+/// Number of bytes to read to get from `start` to `end`, unlimited if there is no end
+fn limit(start: u64, end: Option<u64>) -> u64 {
+    end.map_or(u64::MAX, |end| end - start)
 }
 
-fn run2<T: Search>(cli: &Cli) -> Result<(), BgrepError> {
-    let bgrep: Bgrep<T> = Bgrep::new(cli)?;
+pub fn run() -> Result<(), BgrepError> {
+    let matches = Cli::command().get_matches();
+    let mut cli = Cli::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+    if cli.carve.is_some()
+        && let Some(file) = cli.pattern.take()
+    {
+        // START and END replace PATTERN, so the first positional argument is a file
+        if matches.value_source("file") == Some(ValueSource::DefaultValue) {
+            cli.file.clear();
+        }
+        cli.file.insert(0, file);
+    }
+    let bgrep = Bgrep::new(&cli)?;
+    if let Some(pid) = cli.pid {
+        return bgrep.grep_process(pid, cli.maps.as_deref(), cli.map_name.as_deref());
+    }
     for file in &cli.file {
         bgrep.grep(file)?;
     }
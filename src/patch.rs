@@ -0,0 +1,495 @@
+use std::cmp;
+use std::io;
+use std::io::{Read, Write};
+
+use binarygrep_core::BgrepError;
+
+use crate::replace::{Edit, apply_edits};
+
+/// Offset whose big-endian encoding reads "EOF" and thus cannot start an IPS record
+const IPS_EOF: usize = 0x454f46;
+/// First offset that cannot be addressed by the 24 bit offsets of IPS records
+const IPS_MAX_OFFSET: usize = 1 << 24;
+const IPS_MAX_RECORD: usize = 0xffff;
+
+const BPS_SOURCE_READ: u64 = 0;
+const BPS_TARGET_READ: u64 = 1;
+const BPS_SOURCE_COPY: u64 = 2;
+
+/// File format of a patch
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PatchFormat {
+    Ips,
+    Bps,
+}
+
+impl PatchFormat {
+    /// Determine the format from the extension of `path`
+    pub fn from_path(path: &str) -> Result<PatchFormat, BgrepError> {
+        let lower = path.to_ascii_lowercase();
+        if lower.ends_with(".ips") {
+            Ok(PatchFormat::Ips)
+        } else if lower.ends_with(".bps") {
+            Ok(PatchFormat::Bps)
+        } else {
+            Err(BgrepError(format!(
+                "Cannot determine patch format of '{}' (use the extension .ips or .bps)",
+                path
+            )))
+        }
+    }
+
+    /// Create a patch that applies `edits`, which must be sorted and not overlap, to `input`
+    pub fn create(&self, input: &mut impl Read, edits: &[Edit]) -> Result<Vec<u8>, BgrepError> {
+        match self {
+            PatchFormat::Ips => create_ips(input, edits),
+            PatchFormat::Bps => create_bps(input, edits),
+        }
+    }
+}
+
+fn read_error(err: io::Error) -> BgrepError {
+    BgrepError(format!("Error while reading: {}", err))
+}
+
+/// Create an IPS patch
+///
+/// IPS records overwrite bytes at offsets below 16 MiB, so the patch can neither change the size
+/// of the input nor modify bytes beyond that.
+fn create_ips(input: &mut impl Read, edits: &[Edit]) -> Result<Vec<u8>, BgrepError> {
+    // Adjacent edits are merged into a single record
+    let mut records: Vec<(usize, Vec<u8>)> = vec![];
+    for edit in edits {
+        if edit.new.len() != edit.old.len() {
+            return Err(BgrepError(format!(
+                "IPS patches cannot change the size of the input (edit at offset {:08x}), use a .bps patch",
+                edit.offset
+            )));
+        }
+        if edit.offset + edit.new.len() > IPS_MAX_OFFSET {
+            return Err(BgrepError(format!(
+                "IPS patches cannot modify bytes beyond 16 MiB (edit at offset {:08x}), use a .bps patch",
+                edit.offset
+            )));
+        }
+        match records.last_mut() {
+            Some((offset, data)) if *offset + data.len() == edit.offset => {
+                data.extend_from_slice(&edit.new)
+            }
+            _ if edit.new.is_empty() => {}
+            _ => records.push((edit.offset, edit.new.clone())),
+        }
+    }
+
+    let mut patch = b"PATCH".to_vec();
+    // Absolute offset of the next byte read from `input`
+    let mut position = 0;
+    for (mut offset, data) in records {
+        let mut data = &data[..];
+        let mut previous = None;
+        while !data.is_empty() {
+            let (start, mut record) = if offset == IPS_EOF {
+                // Start one byte earlier and write the byte that is already there
+                let byte = match previous {
+                    Some(byte) => byte,
+                    None => {
+                        io::copy(
+                            &mut input.by_ref().take((IPS_EOF - 1 - position) as u64),
+                            &mut io::sink(),
+                        )
+                        .map_err(read_error)?;
+                        let mut byte = [0];
+                        input.read_exact(&mut byte).map_err(read_error)?;
+                        position = IPS_EOF;
+                        byte[0]
+                    }
+                };
+                (offset - 1, vec![byte])
+            } else {
+                (offset, vec![])
+            };
+            let n = cmp::min(IPS_MAX_RECORD - record.len(), data.len());
+            record.extend_from_slice(&data[..n]);
+            patch.extend_from_slice(&ips_record(start, &record));
+            previous = record.last().copied();
+            offset += n;
+            data = &data[n..];
+        }
+    }
+    patch.extend_from_slice(b"EOF");
+    Ok(patch)
+}
+
+fn ips_record(offset: usize, data: &[u8]) -> Vec<u8> {
+    let mut record = (offset as u32).to_be_bytes()[1..].to_vec();
+    record.extend_from_slice(&(data.len() as u16).to_be_bytes());
+    record.extend_from_slice(data);
+    record
+}
+
+/// Create a BPS patch
+///
+/// Unchanged bytes are copied from the input, the new bytes of each edit are stored in the
+/// patch.  The checksums of the input and of the result are computed while streaming through
+/// the input.
+fn create_bps(input: &mut impl Read, edits: &[Edit]) -> Result<Vec<u8>, BgrepError> {
+    let mut actions = vec![];
+    // Offsets in the input and in the result
+    let mut source = 0;
+    let mut target = 0;
+    // Offset in the input following the last source copy
+    let mut source_relative = 0;
+    for edit in edits {
+        let unchanged = (edit.offset - source) as u64;
+        if unchanged > 0 {
+            if source == target {
+                bps_action(&mut actions, BPS_SOURCE_READ, unchanged);
+            } else {
+                bps_action(&mut actions, BPS_SOURCE_COPY, unchanged);
+                bps_signed(&mut actions, source as i64 - source_relative as i64);
+                source_relative = edit.offset;
+            }
+        }
+        if !edit.new.is_empty() {
+            bps_action(&mut actions, BPS_TARGET_READ, edit.new.len() as u64);
+            actions.extend_from_slice(&edit.new);
+        }
+        source = edit.offset + edit.old.len();
+        target += unchanged as usize + edit.new.len();
+    }
+
+    let mut source_crc = Crc32::new();
+    let mut target_crc = Crc32::new();
+    apply_edits(
+        &mut Tee {
+            inner: input,
+            crc: &mut source_crc,
+        },
+        &mut target_crc,
+        edits,
+    )
+    .map_err(read_error)?;
+    let unchanged = source_crc.len - source as u64;
+    if unchanged > 0 {
+        if source == target {
+            bps_action(&mut actions, BPS_SOURCE_READ, unchanged);
+        } else {
+            bps_action(&mut actions, BPS_SOURCE_COPY, unchanged);
+            bps_signed(&mut actions, source as i64 - source_relative as i64);
+        }
+    }
+
+    let mut patch = b"BPS1".to_vec();
+    bps_number(&mut patch, source_crc.len);
+    bps_number(&mut patch, target_crc.len);
+    // No metadata
+    bps_number(&mut patch, 0);
+    patch.extend_from_slice(&actions);
+    patch.extend_from_slice(&source_crc.value().to_le_bytes());
+    patch.extend_from_slice(&target_crc.value().to_le_bytes());
+    let patch_crc = crc32(&patch);
+    patch.extend_from_slice(&patch_crc.to_le_bytes());
+    Ok(patch)
+}
+
+/// Append the variable length encoding of `number` used by BPS
+fn bps_number(patch: &mut Vec<u8>, mut number: u64) {
+    loop {
+        let x = (number & 0x7f) as u8;
+        number >>= 7;
+        if number == 0 {
+            patch.push(0x80 | x);
+            break;
+        }
+        patch.push(x);
+        number -= 1;
+    }
+}
+
+fn bps_signed(patch: &mut Vec<u8>, number: i64) {
+    bps_number(
+        patch,
+        (number.unsigned_abs() << 1) | u64::from(number.is_negative()),
+    );
+}
+
+fn bps_action(patch: &mut Vec<u8>, action: u64, length: u64) {
+    bps_number(patch, ((length - 1) << 2) | action);
+}
+
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut c = i as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 {
+                0xedb88320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+            k += 1;
+        }
+        table[i] = c;
+        i += 1;
+    }
+    table
+};
+
+/// CRC-32 (as used by zlib) of everything written to it
+struct Crc32 {
+    state: u32,
+    len: u64,
+}
+
+impl Crc32 {
+    fn new() -> Crc32 {
+        Crc32 {
+            state: 0xffffffff,
+            len: 0,
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        for &x in data {
+            self.state = CRC32_TABLE[((self.state ^ x as u32) & 0xff) as usize] ^ (self.state >> 8);
+        }
+        self.len += data.len() as u64;
+    }
+
+    fn value(&self) -> u32 {
+        !self.state
+    }
+}
+
+impl Write for Crc32 {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = Crc32::new();
+    crc.update(data);
+    crc.value()
+}
+
+/// Reader that feeds everything read from `inner` into `crc`
+struct Tee<'a, R: Read> {
+    inner: &'a mut R,
+    crc: &'a mut Crc32,
+}
+
+impl<R: Read> Read for Tee<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.crc.update(&buf[..n]);
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply_ips(patch: &[u8], source: &[u8]) -> Vec<u8> {
+        assert_eq!(&patch[..5], b"PATCH");
+        let mut target = source.to_vec();
+        let mut i = 5;
+        loop {
+            let offset = &patch[i..i + 3];
+            if offset == b"EOF" {
+                assert_eq!(i + 3, patch.len());
+                return target;
+            }
+            let offset = u32::from_be_bytes([0, offset[0], offset[1], offset[2]]) as usize;
+            let size = u16::from_be_bytes([patch[i + 3], patch[i + 4]]) as usize;
+            assert!(size > 0);
+            target[offset..offset + size].copy_from_slice(&patch[i + 5..i + 5 + size]);
+            i += 5 + size;
+        }
+    }
+
+    fn read_number(patch: &[u8], i: &mut usize) -> u64 {
+        let mut data = 0;
+        let mut shift = 1;
+        loop {
+            let x = patch[*i] as u64;
+            *i += 1;
+            data += (x & 0x7f) * shift;
+            if x & 0x80 != 0 {
+                return data;
+            }
+            shift <<= 7;
+            data += shift;
+        }
+    }
+
+    fn apply_bps(patch: &[u8], source: &[u8]) -> Vec<u8> {
+        assert_eq!(&patch[..4], b"BPS1");
+        let footer = patch.len() - 12;
+        let crc = |i: usize| u32::from_le_bytes(patch[i..i + 4].try_into().unwrap());
+        assert_eq!(crc(footer + 8), crc32(&patch[..footer + 8]));
+        assert_eq!(crc(footer), crc32(source));
+        let mut i = 4;
+        assert_eq!(read_number(patch, &mut i), source.len() as u64);
+        let target_size = read_number(patch, &mut i) as usize;
+        let metadata_size = read_number(patch, &mut i) as usize;
+        i += metadata_size;
+        let mut target = vec![];
+        let mut source_relative = 0i64;
+        let mut target_relative = 0i64;
+        while i < footer {
+            let data = read_number(patch, &mut i);
+            let length = (data >> 2) as usize + 1;
+            match data & 3 {
+                0 => target.extend_from_slice(&source[target.len()..target.len() + length]),
+                1 => {
+                    target.extend_from_slice(&patch[i..i + length]);
+                    i += length;
+                }
+                2 => {
+                    let offset = read_number(patch, &mut i);
+                    let sign = if offset & 1 != 0 { -1 } else { 1 };
+                    source_relative += sign * (offset >> 1) as i64;
+                    let start = source_relative as usize;
+                    target.extend_from_slice(&source[start..start + length]);
+                    source_relative += length as i64;
+                }
+                _ => {
+                    let offset = read_number(patch, &mut i);
+                    let sign = if offset & 1 != 0 { -1 } else { 1 };
+                    target_relative += sign * (offset >> 1) as i64;
+                    for _ in 0..length {
+                        target.push(target[target_relative as usize]);
+                        target_relative += 1;
+                    }
+                }
+            }
+        }
+        assert_eq!(target.len(), target_size);
+        assert_eq!(crc(footer + 4), crc32(&target));
+        target
+    }
+
+    fn edit(offset: usize, old: &[u8], new: &[u8]) -> Edit {
+        Edit {
+            offset,
+            old: old.to_vec(),
+            new: new.to_vec(),
+        }
+    }
+
+    fn apply(source: &[u8], edits: &[Edit]) -> Vec<u8> {
+        let mut target = vec![];
+        apply_edits(&mut &source[..], &mut target, edits).unwrap();
+        target
+    }
+
+    fn source(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i * 7 % 251) as u8).collect()
+    }
+
+    #[test]
+    fn test_from_path() {
+        assert_eq!(PatchFormat::from_path("a.ips").ok(), Some(PatchFormat::Ips));
+        assert_eq!(
+            PatchFormat::from_path("dir/A.BPS").ok(),
+            Some(PatchFormat::Bps)
+        );
+        assert!(PatchFormat::from_path("a.patch").is_err());
+    }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xcbf43926);
+    }
+
+    #[test]
+    fn test_bps_number() {
+        let mut patch = vec![];
+        for number in [0, 1, 127, 128, 129, 16511, 16512, 1 << 40] {
+            bps_number(&mut patch, number);
+        }
+        let mut i = 0;
+        for number in [0, 1, 127, 128, 129, 16511, 16512, 1 << 40] {
+            assert_eq!(read_number(&patch, &mut i), number);
+        }
+        assert_eq!(i, patch.len());
+    }
+
+    #[test]
+    fn test_ips_round_trip() {
+        let source = source(1000);
+        let edits = [
+            edit(0, &source[0..2], &[1, 2]),
+            edit(2, &source[2..3], &[3]),
+            edit(500, &source[500..504], &[0, 0, 0, 0]),
+            edit(999, &source[999..], &[9]),
+        ];
+        let patch = PatchFormat::Ips.create(&mut &source[..], &edits).unwrap();
+        assert_eq!(&patch[5..13], &[0, 0, 0, 0, 3, 1, 2, 3]);
+        assert_eq!(apply_ips(&patch, &source), apply(&source, &edits));
+    }
+
+    #[test]
+    fn test_ips_eof_offset() {
+        let source = source(IPS_EOF + 0x30000);
+        let edits = [
+            edit(IPS_EOF, &source[IPS_EOF..IPS_EOF + 2], &[1, 2]),
+            edit(
+                IPS_EOF + 0x100,
+                &source[IPS_EOF + 0x100..IPS_EOF + 0x100 + 0x1fffe],
+                &vec![7; 0x1fffe],
+            ),
+        ];
+        let patch = PatchFormat::Ips.create(&mut &source[..], &edits).unwrap();
+        assert_eq!(&patch[5..8], &[0x45, 0x4f, 0x45]);
+        assert_eq!(apply_ips(&patch, &source), apply(&source, &edits));
+
+        // A record that is split exactly at the EOF offset
+        let start = IPS_EOF - IPS_MAX_RECORD;
+        let edits = [edit(start, &source[start..start + 0x20000], &[5; 0x20000])];
+        let patch = PatchFormat::Ips.create(&mut &source[..], &edits).unwrap();
+        assert_eq!(apply_ips(&patch, &source), apply(&source, &edits));
+    }
+
+    #[test]
+    fn test_ips_unsupported() {
+        let source = source(100);
+        let edits = [edit(10, &source[10..12], &[1])];
+        assert!(PatchFormat::Ips.create(&mut &source[..], &edits).is_err());
+        let edits = [edit(IPS_MAX_OFFSET, &[0], &[1])];
+        assert!(PatchFormat::Ips.create(&mut &source[..], &edits).is_err());
+    }
+
+    #[test]
+    fn test_bps_round_trip() {
+        let source = source(1000);
+        let edits = [
+            edit(10, &source[10..12], &[1, 2]),
+            edit(100, &source[100..110], &[3]),
+            edit(200, &source[200..201], &[4, 5, 6, 7]),
+            edit(300, &source[300..310], &[]),
+            edit(310, &source[310..311], &[8]),
+            edit(990, &source[990..], &[9; 20]),
+        ];
+        let patch = PatchFormat::Bps.create(&mut &source[..], &edits).unwrap();
+        assert_eq!(apply_bps(&patch, &source), apply(&source, &edits));
+
+        let patch = PatchFormat::Bps
+            .create(&mut &source[..], &edits[..3])
+            .unwrap();
+        assert_eq!(apply_bps(&patch, &source), apply(&source, &edits[..3]));
+
+        let patch = PatchFormat::Bps.create(&mut &source[..], &[]).unwrap();
+        assert_eq!(apply_bps(&patch, &source), source);
+    }
+}
@@ -0,0 +1,173 @@
+use std::fs;
+use std::io::{self, Read};
+use std::os::unix::fs::FileExt;
+
+use binarygrep_core::BgrepError;
+
+/// Error number of reads from pages that cannot be read
+const EIO: i32 = 5;
+
+/// Mapped memory region of a process as listed in `/proc/<PID>/maps`
+#[derive(Debug, PartialEq)]
+pub struct Mapping {
+    pub start: u64,
+    pub end: u64,
+    /// Permissions such as `r-xp`
+    pub perms: String,
+    /// Mapped file or pseudo name such as `[stack]`, empty for anonymous memory
+    pub path: String,
+}
+
+impl Mapping {
+    /// Parse a line of `/proc/<PID>/maps` such as
+    /// `7f0e3c428000-7f0e3c5bd000 r-xp 00028000 fe:01 1049031 /usr/lib/libc.so.6`
+    pub fn parse(line: &str) -> Option<Mapping> {
+        let mut fields = line.splitn(6, ' ');
+        let (start, end) = fields.next()?.split_once('-')?;
+        let perms = fields.next()?.to_string();
+        // Offset, device and inode
+        fields.nth(2)?;
+        Some(Mapping {
+            start: u64::from_str_radix(start, 16).ok()?,
+            end: u64::from_str_radix(end, 16).ok()?,
+            perms,
+            path: fields.next().unwrap_or_default().trim_start().to_string(),
+        })
+    }
+
+    /// Name of the file or pseudo name along the permissions, e.g. `libc.so.6 r-xp`
+    pub fn label(&self) -> String {
+        let name = match self.path.rsplit_once('/') {
+            Some((_, name)) => name,
+            None if self.path.is_empty() => "[anon]",
+            None => &self.path,
+        };
+        format!("{} {}", name, self.perms)
+    }
+
+    pub fn is_readable(&self) -> bool {
+        self.perms.starts_with('r')
+    }
+
+    /// Whether the permissions start with `filter` such as `r-x`, where `?` matches any permission
+    pub fn has_perms(&self, filter: &str) -> bool {
+        filter.len() <= self.perms.len()
+            && filter
+                .bytes()
+                .zip(self.perms.bytes())
+                .all(|(f, p)| f == b'?' || f == p)
+    }
+}
+
+/// Memory regions of the process `pid`
+pub fn mappings(pid: u32) -> Result<Vec<Mapping>, BgrepError> {
+    let maps = fs::read_to_string(format!("/proc/{}/maps", pid))
+        .map_err(|err| BgrepError(format!("Cannot read mappings of process {}: {}", pid, err)))?;
+    maps.lines()
+        .map(|line| {
+            Mapping::parse(line).ok_or_else(|| BgrepError(format!("Invalid mapping: {}", line)))
+        })
+        .collect()
+}
+
+/// Open the memory of the process `pid`
+pub fn open_memory(pid: u32) -> Result<fs::File, BgrepError> {
+    fs::File::open(format!("/proc/{}/mem", pid))
+        .map_err(|err| BgrepError(format!("Cannot open memory of process {}: {}", pid, err)))
+}
+
+/// Reader of a memory region, which ends early at the first page that cannot be read
+pub struct Region<'a> {
+    mem: &'a fs::File,
+    position: u64,
+    end: u64,
+}
+
+impl<'a> Region<'a> {
+    pub fn new(mem: &'a fs::File, mapping: &Mapping) -> Region<'a> {
+        Region {
+            mem,
+            position: mapping.start,
+            end: mapping.end,
+        }
+    }
+}
+
+impl Read for Region<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = buf.len().min((self.end - self.position) as usize);
+        if len == 0 {
+            return Ok(0);
+        }
+        match self.mem.read_at(&mut buf[..len], self.position) {
+            Ok(n) => {
+                self.position += n as u64;
+                Ok(n)
+            }
+            // Pages such as those of [vvar] cannot be read through /proc/<PID>/mem
+            Err(err) if err.raw_os_error() == Some(EIO) => {
+                self.position = self.end;
+                Ok(0)
+            }
+            Err(err) => Err(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let mapping = Mapping::parse(
+            "7f0e3c428000-7f0e3c5bd000 r-xp 00028000 fe:01 1049031    /usr/lib/libc.so.6",
+        )
+        .unwrap();
+        assert_eq!(
+            mapping,
+            Mapping {
+                start: 0x7f0e3c428000,
+                end: 0x7f0e3c5bd000,
+                perms: "r-xp".to_string(),
+                path: "/usr/lib/libc.so.6".to_string(),
+            }
+        );
+        assert_eq!(mapping.label(), "libc.so.6 r-xp");
+        assert!(mapping.is_readable());
+        assert!(mapping.has_perms("r-x"));
+        assert!(mapping.has_perms("??x"));
+        assert!(!mapping.has_perms("--x"));
+        assert!(!mapping.has_perms("rw?"));
+        assert!(!mapping.has_perms("r-xp-"));
+        let mapping =
+            Mapping::parse("7f0e3c400000-7f0e3c428000 rwxp 00000000 00:00 0 [anon:jit]").unwrap();
+        assert!(!mapping.has_perms("r-x"));
+        assert!(mapping.has_perms("r?x"));
+        let mapping =
+            Mapping::parse("7ffd4a1e5000-7ffd4a206000 rw-p 00000000 00:00 0 [stack]").unwrap();
+        assert_eq!(mapping.label(), "[stack] rw-p");
+        let mapping = Mapping::parse("7f0e3c400000-7f0e3c428000 ---p 00000000 00:00 0").unwrap();
+        assert_eq!(mapping.label(), "[anon] ---p");
+        assert!(!mapping.is_readable());
+        assert!(Mapping::parse("7f0e3c400000 ---p").is_none());
+    }
+
+    #[test]
+    fn test_region() {
+        let mappings = mappings(std::process::id()).unwrap();
+        let mem = open_memory(std::process::id()).unwrap();
+        let data = [0x5a, 0xa5, 0x3c, 0xc3];
+        let address = std::hint::black_box(&data).as_ptr() as u64;
+        let mapping = mappings
+            .iter()
+            .find(|m| m.start <= address && address < m.end)
+            .unwrap();
+        let mut region = Region::new(&mem, mapping);
+        let mut bytes = vec![];
+        region.read_to_end(&mut bytes).unwrap();
+        let offset = (address - mapping.start) as usize;
+        assert_eq!(bytes.len() as u64, mapping.end - mapping.start);
+        assert_eq!(bytes[offset..offset + 4], data);
+    }
+}
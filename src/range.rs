@@ -0,0 +1,156 @@
+use binarygrep_core::BgrepError;
+
+/// Position within an input, counted either from its start or from its end
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Position {
+    FromStart(u64),
+    FromEnd(u64),
+}
+
+/// Parse a position such as `4096`, `0x1000`, `4k` or `-1M`
+///
+/// The suffixes `k`, `M` and `G` multiply by powers of 1024.  A leading `-` counts the position
+/// from the end of the input.
+pub fn parse_position(input: &str) -> Result<Position, String> {
+    let (from_end, value) = match input.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, input),
+    };
+    let (digits, multiplier) = match value.char_indices().last() {
+        Some((i, 'k' | 'K')) => (&value[..i], 1 << 10),
+        Some((i, 'M')) => (&value[..i], 1 << 20),
+        Some((i, 'G')) => (&value[..i], 1 << 30),
+        _ => (value, 1),
+    };
+    let number = if let Some(hex) = digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        u64::from_str_radix(hex, 16)
+    } else {
+        digits.parse::<u64>()
+    }
+    .map_err(|err| format!("Invalid number '{}': {}", input, err))?;
+    let number = number
+        .checked_mul(multiplier)
+        .ok_or_else(|| format!("Number too large: {}", input))?;
+    if from_end {
+        Ok(Position::FromEnd(number))
+    } else {
+        Ok(Position::FromStart(number))
+    }
+}
+
+/// Parse a size such as `4096`, `0x1000` or `4k`
+pub fn parse_size(input: &str) -> Result<u64, String> {
+    match parse_position(input)? {
+        Position::FromStart(size) => Ok(size),
+        Position::FromEnd(_) => Err(format!("Size must not be negative: {}", input)),
+    }
+}
+
+/// Part of each input that is searched
+#[derive(Default)]
+pub struct Range {
+    pub skip: Option<Position>,
+    pub length: Option<Position>,
+    pub end: Option<Position>,
+}
+
+impl Range {
+    /// Whether the size of the input is required to resolve the range
+    pub fn needs_size(&self) -> bool {
+        [self.skip, self.length, self.end]
+            .iter()
+            .any(|p| matches!(p, Some(Position::FromEnd(_))))
+    }
+
+    /// Resolve the range to absolute offsets `[start, end)` for an input of `size` bytes
+    ///
+    /// `size` may only be `None` if `needs_size()` is false.  An end of `None` means that the
+    /// input is searched till EOF.
+    pub fn resolve(&self, size: Option<u64>) -> Result<(u64, Option<u64>), BgrepError> {
+        let absolute = |position: Position| match (position, size) {
+            (Position::FromStart(n), Some(size)) => Ok(n.min(size)),
+            (Position::FromStart(n), None) => Ok(n),
+            (Position::FromEnd(n), Some(size)) => Ok(size.saturating_sub(n)),
+            (Position::FromEnd(_), None) => Err(BgrepError(String::from(
+                "Offsets relative to the end require an input of known size",
+            ))),
+        };
+        let start = match self.skip {
+            Some(position) => absolute(position)?,
+            None => 0,
+        };
+        let end = match (self.end, self.length) {
+            (Some(position), _) => Some(absolute(position)?),
+            (None, Some(Position::FromStart(n))) => Some(start.saturating_add(n)),
+            (None, Some(position)) => Some(absolute(position)?),
+            (None, None) => None,
+        };
+        let end = end.map(|end| size.map_or(end, |size| end.min(size)).max(start));
+        Ok((start, end))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_position_valid() {
+        assert_eq!(parse_position("123"), Ok(Position::FromStart(123)));
+        assert_eq!(parse_position("0x1f"), Ok(Position::FromStart(0x1f)));
+        assert_eq!(parse_position("4k"), Ok(Position::FromStart(4096)));
+        assert_eq!(parse_position("2M"), Ok(Position::FromStart(2 << 20)));
+        assert_eq!(parse_position("1G"), Ok(Position::FromStart(1 << 30)));
+        assert_eq!(parse_position("-16"), Ok(Position::FromEnd(16)));
+        assert_eq!(parse_position("-0x10k"), Ok(Position::FromEnd(16 << 10)));
+    }
+
+    #[test]
+    fn test_parse_position_invalid() {
+        assert!(parse_position("").is_err());
+        assert!(parse_position("-").is_err());
+        assert!(parse_position("12x").is_err());
+        assert!(parse_position("0xfg").is_err());
+        assert!(parse_position("--5").is_err());
+        assert!(parse_position("99999999999G").is_err());
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("16k"), Ok(16384));
+        assert!(parse_size("-16k").is_err());
+    }
+
+    #[test]
+    fn test_resolve() {
+        let range = Range {
+            skip: Some(Position::FromStart(10)),
+            length: Some(Position::FromStart(20)),
+            end: None,
+        };
+        assert!(!range.needs_size());
+        assert_eq!(range.resolve(None).unwrap(), (10, Some(30)));
+        assert_eq!(range.resolve(Some(15)).unwrap(), (10, Some(15)));
+
+        let range = Range {
+            skip: Some(Position::FromEnd(10)),
+            length: None,
+            end: Some(Position::FromEnd(4)),
+        };
+        assert!(range.needs_size());
+        assert!(range.resolve(None).is_err());
+        assert_eq!(range.resolve(Some(100)).unwrap(), (90, Some(96)));
+        assert_eq!(range.resolve(Some(5)).unwrap(), (0, Some(1)));
+
+        let range = Range {
+            skip: Some(Position::FromStart(50)),
+            length: Some(Position::FromEnd(60)),
+            end: None,
+        };
+        assert_eq!(range.resolve(Some(100)).unwrap(), (50, Some(50)));
+        assert_eq!(range.resolve(Some(20)).unwrap(), (20, Some(20)));
+    }
+}
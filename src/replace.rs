@@ -0,0 +1,294 @@
+use std::fs;
+use std::io;
+use std::io::{Read, Write};
+use std::ops::Range;
+use std::path::Path;
+
+use binarygrep_core::{BgrepError, decode_hex};
+
+use crate::extract::write_error;
+use crate::patch::PatchFormat;
+
+#[derive(Debug, PartialEq)]
+enum ReplacementPart {
+    Bytes(Vec<u8>),
+    /// Bytes of the capture with the given 1-based index
+    Capture(usize),
+}
+
+/// Bytes that replace each match, e.g. `9090 \1 ff`
+#[derive(Debug, PartialEq)]
+pub struct Replacement {
+    parts: Vec<ReplacementPart>,
+}
+
+impl Replacement {
+    pub fn new(input: &str) -> Result<Replacement, BgrepError> {
+        let mut parts = vec![];
+        let mut rest = input;
+        while !rest.is_empty() {
+            if let Some(reference) = rest.strip_prefix('\\') {
+                let digits = reference
+                    .find(|c: char| !c.is_ascii_digit())
+                    .unwrap_or(reference.len());
+                let index = reference[..digits].parse::<usize>().map_err(|err| {
+                    BgrepError(format!(
+                        "Invalid reference in replacement '{}': {}",
+                        input, err
+                    ))
+                })?;
+                if index == 0 {
+                    return Err(BgrepError(format!(
+                        "Invalid reference in replacement '{}': captures start at \\1",
+                        input
+                    )));
+                }
+                parts.push(ReplacementPart::Capture(index));
+                rest = &reference[digits..];
+            } else {
+                let end = rest.find('\\').unwrap_or(rest.len());
+                let bytes = decode_hex(&rest[..end])?;
+                if !bytes.is_empty() {
+                    parts.push(ReplacementPart::Bytes(bytes));
+                }
+                rest = &rest[end..];
+            }
+        }
+        Ok(Replacement { parts })
+    }
+
+    /// Highest capture index referenced by the replacement
+    pub fn max_capture(&self) -> usize {
+        self.parts
+            .iter()
+            .map(|part| match part {
+                ReplacementPart::Capture(index) => *index,
+                ReplacementPart::Bytes(_) => 0,
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Bytes that replace `matched` whose captures are the given ranges of `matched`
+    pub fn apply(&self, matched: &[u8], captures: &[Range<usize>]) -> Result<Vec<u8>, BgrepError> {
+        let mut result = vec![];
+        for part in &self.parts {
+            match part {
+                ReplacementPart::Bytes(bytes) => result.extend_from_slice(bytes),
+                ReplacementPart::Capture(index) => {
+                    let range = captures
+                        .get(index - 1)
+                        .ok_or_else(|| BgrepError(format!("Pattern has no capture \\{}", index)))?;
+                    result.extend_from_slice(&matched[range.clone()]);
+                }
+            }
+        }
+        Ok(result)
+    }
+}
+
+/// Where the result of the replacement is written to
+pub enum Target {
+    /// The given file, "-" is standard output
+    Output(String),
+    /// The input file, the original is renamed by appending the suffix unless it is empty
+    InPlace(String),
+    /// A patch in the given format that applies the changes, written to the given file ("-" is
+    /// standard output)
+    Patch(PatchFormat, String),
+    /// Only print the changes
+    DryRun,
+}
+
+pub struct Replace {
+    pub replacement: Replacement,
+    pub target: Target,
+    pub allow_resize: bool,
+}
+
+/// Replacement of the bytes `old` at `offset` by `new`
+pub struct Edit {
+    pub offset: usize,
+    pub old: Vec<u8>,
+    pub new: Vec<u8>,
+}
+
+impl Replace {
+    /// Create the edit that replaces the bytes `old` found at `offset`
+    pub fn edit(
+        &self,
+        offset: usize,
+        old: Vec<u8>,
+        captures: &[Range<usize>],
+    ) -> Result<Edit, BgrepError> {
+        let new = self.replacement.apply(&old, captures)?;
+        if new.len() != old.len() && !self.allow_resize {
+            return Err(BgrepError(format!(
+                "Replacement of {} bytes at offset {:08x} has a length of {} bytes (use --allow-resize)",
+                old.len(),
+                offset,
+                new.len()
+            )));
+        }
+        Ok(Edit { offset, old, new })
+    }
+
+    /// Whether `path` is a backup of a file modified in place, which a recursive search skips
+    pub fn is_backup(&self, path: &Path) -> bool {
+        match &self.target {
+            Target::InPlace(suffix) if !suffix.is_empty() => path
+                .file_name()
+                .is_some_and(|name| name.as_encoded_bytes().ends_with(suffix.as_bytes())),
+            _ => false,
+        }
+    }
+
+    /// Write `input`, which has been read from `path`, with `edits` applied to the target
+    pub fn write(
+        &self,
+        path: &str,
+        input: &mut impl Read,
+        edits: &[Edit],
+    ) -> Result<(), BgrepError> {
+        match &self.target {
+            Target::Output(output) if output == "-" => {
+                let mut out = io::stdout().lock();
+                apply_edits(input, &mut out, edits).map_err(write_error)
+            }
+            Target::Output(output) => write_file(output, input, edits),
+            // Files without matches are left untouched, including their backup
+            Target::InPlace(_) if edits.is_empty() => Ok(()),
+            Target::InPlace(suffix) => {
+                let backup = format!("{}{}", path, suffix);
+                if !suffix.is_empty() && fs::symlink_metadata(&backup).is_ok() {
+                    return Err(BgrepError(format!(
+                        "Cannot replace file '{}': backup '{}' already exists",
+                        path, backup
+                    )));
+                }
+                let tmp = format!("{}.bgrep-tmp", path);
+                write_file(&tmp, input, edits)?;
+                let permissions = fs::metadata(path).map(|metadata| metadata.permissions());
+                let result = permissions
+                    .and_then(|permissions| fs::set_permissions(&tmp, permissions))
+                    .and_then(|_| {
+                        if suffix.is_empty() {
+                            Ok(())
+                        } else {
+                            fs::rename(path, &backup)
+                        }
+                    })
+                    .and_then(|_| fs::rename(&tmp, path));
+                result.map_err(|err| {
+                    let _ = fs::remove_file(&tmp);
+                    BgrepError(format!("Cannot replace file '{}': {}", path, err))
+                })
+            }
+            Target::Patch(format, output) => {
+                let patch = format.create(input, edits)?;
+                if output == "-" {
+                    io::stdout().lock().write_all(&patch)
+                } else {
+                    fs::write(output, &patch)
+                }
+                .map_err(|err| BgrepError(format!("Cannot write patch '{}': {}", output, err)))
+            }
+            Target::DryRun => Ok(()),
+        }
+    }
+}
+
+fn write_file(path: &str, input: &mut impl Read, edits: &[Edit]) -> Result<(), BgrepError> {
+    let f = fs::File::create(Path::new(path))
+        .map_err(|err| BgrepError(format!("Cannot create file '{}': {}", path, err)))?;
+    let mut out = io::BufWriter::new(f);
+    apply_edits(input, &mut out, edits)
+        .and_then(|_| out.flush())
+        .map_err(|err| {
+            let _ = fs::remove_file(path);
+            write_error(err)
+        })
+}
+
+/// Copy `input` to `output` while applying `edits`, which must be sorted and not overlap
+pub fn apply_edits(
+    input: &mut impl Read,
+    output: &mut impl Write,
+    edits: &[Edit],
+) -> io::Result<()> {
+    let mut position = 0;
+    for edit in edits {
+        io::copy(
+            &mut input.by_ref().take((edit.offset - position) as u64),
+            output,
+        )?;
+        io::copy(
+            &mut input.by_ref().take(edit.old.len() as u64),
+            &mut io::sink(),
+        )?;
+        output.write_all(&edit.new)?;
+        position = edit.offset + edit.old.len();
+    }
+    io::copy(input, output)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replacement_valid() {
+        let replacement = Replacement::new("90 90\\2ff\\10").unwrap();
+        assert_eq!(
+            replacement.parts,
+            vec![
+                ReplacementPart::Bytes(vec![0x90, 0x90]),
+                ReplacementPart::Capture(2),
+                ReplacementPart::Bytes(vec![0xff]),
+                ReplacementPart::Capture(10),
+            ]
+        );
+        assert_eq!(replacement.max_capture(), 10);
+        assert_eq!(Replacement::new("").unwrap().parts, vec![]);
+    }
+
+    #[test]
+    fn test_replacement_invalid() {
+        assert!(Replacement::new("909").is_err());
+        assert!(Replacement::new("9g").is_err());
+        assert!(Replacement::new("90\\").is_err());
+        assert!(Replacement::new("90\\0").is_err());
+        assert!(Replacement::new("9\\19").is_err());
+    }
+
+    #[test]
+    fn test_replacement_apply() {
+        let replacement = Replacement::new("\\2 00 \\1").unwrap();
+        assert_eq!(
+            replacement.apply(&[1, 2, 3, 4], &[0..1, 2..4]).unwrap(),
+            vec![3, 4, 0, 1]
+        );
+        let replacement = Replacement::new("\\3").unwrap();
+        assert!(replacement.apply(&[1, 2, 3, 4], &[0..1, 2..4]).is_err());
+    }
+
+    #[test]
+    fn test_apply_edits() {
+        let edits = [
+            Edit {
+                offset: 1,
+                old: vec![1, 2],
+                new: vec![9],
+            },
+            Edit {
+                offset: 4,
+                old: vec![4],
+                new: vec![8, 8],
+            },
+        ];
+        let mut output = vec![];
+        apply_edits(&mut &[0u8, 1, 2, 3, 4, 5][..], &mut output, &edits).unwrap();
+        assert_eq!(output, vec![0, 9, 3, 8, 8, 5]);
+    }
+}
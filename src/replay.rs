@@ -0,0 +1,70 @@
+use std::cell::Cell;
+use std::io::{self, Read};
+use std::rc::Rc;
+
+/// Reader that keeps the bytes it has read until recording is stopped
+///
+/// Data that looks compressed or like an archive is searched as it is if unpacking it fails
+/// before anything has been searched, which needs the bytes the decoder has already consumed.
+pub struct Replay<R> {
+    inner: R,
+    recorded: Vec<u8>,
+    recording: Rc<Cell<bool>>,
+}
+
+impl<R: Read> Replay<R> {
+    pub fn new(inner: R) -> Replay<R> {
+        Replay {
+            inner,
+            recorded: vec![],
+            recording: Rc::new(Cell::new(true)),
+        }
+    }
+
+    /// Flag that stops the recording when cleared, usable while the reader is borrowed
+    pub fn recording(&self) -> Rc<Cell<bool>> {
+        self.recording.clone()
+    }
+
+    /// All data from the start if still recording, `None` otherwise
+    pub fn replay(self) -> Option<impl Read> {
+        self.recording
+            .get()
+            .then(|| io::Cursor::new(self.recorded).chain(self.inner))
+    }
+}
+
+impl<R: Read> Read for Replay<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        if self.recording.get() {
+            self.recorded.extend_from_slice(&buf[..n]);
+        } else if !self.recorded.is_empty() {
+            self.recorded = vec![];
+        }
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replay() {
+        let data = [0x01, 0x02, 0x03, 0x04, 0x05];
+        let mut replay = Replay::new(&data[..]);
+        let mut buf = [0; 2];
+        replay.read_exact(&mut buf).unwrap();
+        let mut replayed = vec![];
+        replay.replay().unwrap().read_to_end(&mut replayed).unwrap();
+        assert_eq!(replayed, data);
+
+        let mut replay = Replay::new(&data[..]);
+        replay.read_exact(&mut buf).unwrap();
+        replay.recording().set(false);
+        replay.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [0x03, 0x04]);
+        assert!(replay.replay().is_none());
+    }
+}
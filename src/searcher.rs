@@ -0,0 +1,558 @@
+use std::cmp;
+use std::collections::VecDeque;
+use std::fs;
+use std::io::Read;
+use std::ops::{ControlFlow, Range};
+use std::path::Path;
+
+use binarygrep_core::{
+    BgrepError, BoyerMooreSearch, BufferMatch, ExtendedSearch, Search, SearchOptions,
+};
+
+use crate::buffer::Buffer;
+
+const BUFFER_SIZE: usize = 4 * 1024 * 1024;
+
+/// Match of a pattern in an input
+#[derive(Clone, Debug, PartialEq)]
+pub struct Match {
+    /// Offset of the first byte of the match in the input
+    pub offset: usize,
+    pub len: usize,
+    /// The matched bytes
+    pub bytes: Vec<u8>,
+    /// Ranges of the captures within `bytes`
+    pub captures: Vec<Range<usize>>,
+}
+
+impl Match {
+    /// Match `m` of the current chunk of `buffer`
+    pub(crate) fn new(buffer: &Buffer, m: BufferMatch) -> Option<Match> {
+        let (buf_a, buf_b) = buffer.view(m.start as isize, (m.start + m.len) as isize)?;
+        Some(Match {
+            offset: buffer.position + m.start,
+            len: m.len,
+            bytes: [buf_a, buf_b].concat(),
+            captures: m.captures,
+        })
+    }
+
+    /// Bytes of the capture with the given 1-based index
+    pub fn capture(&self, n: usize) -> Option<&[u8]> {
+        let range = self.captures.get(n.checked_sub(1)?)?;
+        Some(&self.bytes[range.clone()])
+    }
+}
+
+/// Bytes around a match passed to a [`Sink`]
+pub struct Context<'a> {
+    buffer: &'a Buffer,
+    /// Index of the match in `buffer`
+    start: usize,
+    len: usize,
+    /// Maximum number of bytes available before and after the match
+    size: usize,
+}
+
+impl<'a> Context<'a> {
+    pub(crate) fn new(buffer: &'a Buffer, m: &BufferMatch, size: usize) -> Context<'a> {
+        Context {
+            buffer,
+            start: m.start,
+            len: m.len,
+            size,
+        }
+    }
+
+    /// Up to `n` bytes preceding the match, at most the context of the searcher and fewer at the
+    /// start of the input
+    pub fn before(&self, n: usize) -> Vec<u8> {
+        let n = cmp::min(n, self.size);
+        let start = cmp::max(self.start as isize - n as isize, self.buffer.min_index);
+        self.view(start, self.start as isize)
+    }
+
+    /// Up to `n` bytes following the match, at most the context of the searcher and fewer at the
+    /// end of the input
+    pub fn after(&self, n: usize) -> Vec<u8> {
+        let n = cmp::min(n, self.size);
+        let end = cmp::min((self.start + self.len + n) as isize, self.buffer.max_index);
+        self.view((self.start + self.len) as isize, end)
+    }
+
+    fn view(&self, start: isize, end: isize) -> Vec<u8> {
+        self.buffer
+            .view(start, end)
+            .map(|(buf_a, buf_b)| [buf_a, buf_b].concat())
+            .unwrap_or_default()
+    }
+}
+
+/// Receives the matches of a search one at a time
+///
+/// Unlike [`Matches`], which holds all matches of the chunk of the input that is currently
+/// searched, a search into a sink uses the same amount of memory regardless of the number of
+/// matches.
+pub trait Sink {
+    /// Called for each match in the order of their offsets, `ControlFlow::Break` stops the
+    /// search
+    fn matched(&mut self, m: &Match, ctx: &Context) -> ControlFlow<()>;
+}
+
+impl<F: FnMut(&Match, &Context) -> ControlFlow<()>> Sink for F {
+    fn matched(&mut self, m: &Match, ctx: &Context) -> ControlFlow<()> {
+        self(m, ctx)
+    }
+}
+
+/// Builder for a [`Searcher`]
+#[derive(Clone, Default)]
+pub struct SearcherBuilder {
+    pattern: String,
+    extended: bool,
+    context: usize,
+    options: SearchOptions,
+}
+
+impl SearcherBuilder {
+    /// Pattern as hexadecimal string, or in the extended syntax if `extended` is set
+    pub fn pattern(mut self, pattern: &str) -> SearcherBuilder {
+        self.pattern = String::from(pattern);
+        self
+    }
+
+    /// Enable extended search patterns with wildcards, character sets, quantifiers, groups,
+    /// back-references, fields and anchors
+    pub fn extended(mut self, extended: bool) -> SearcherBuilder {
+        self.extended = extended;
+        self
+    }
+
+    /// Only report matches whose offset is a multiple of `align`
+    pub fn align(mut self, align: usize) -> SearcherBuilder {
+        self.options.align = align;
+        self
+    }
+
+    /// Only report matches whose offset minus `align_offset` is a multiple of `align`
+    pub fn align_offset(mut self, align_offset: usize) -> SearcherBuilder {
+        self.options.align_offset = align_offset;
+        self
+    }
+
+    /// Do not report matches that overlap a previously reported match
+    pub fn no_overlap(mut self, no_overlap: bool) -> SearcherBuilder {
+        self.options.no_overlap = no_overlap;
+        self
+    }
+
+    /// Number of bytes before and after each match that are available from the [`Context`]
+    /// passed to a [`Sink`]
+    pub fn context(mut self, context: usize) -> SearcherBuilder {
+        self.context = context;
+        self
+    }
+
+    pub fn build(&self) -> Result<Searcher, BgrepError> {
+        if self.options.align == 0 {
+            return Err(BgrepError(String::from("Alignment must be at least 1")));
+        }
+        let search: Box<dyn Search> = if self.extended {
+            Box::new(ExtendedSearch::new(&self.pattern, self.options)?)
+        } else {
+            Box::new(BoyerMooreSearch::new(&self.pattern, self.options)?)
+        };
+        Ok(Searcher {
+            search,
+            context: self.context,
+            no_overlap: self.options.no_overlap,
+        })
+    }
+}
+
+/// Searches a pattern in inputs
+///
+/// ```
+/// use binarygrep::Searcher;
+///
+/// let searcher = Searcher::builder()
+///     .pattern("4d5a .{2} (.{2})")
+///     .extended(true)
+///     .build()?;
+/// let data = b"..MZ\x00\x00\x80\x01..";
+/// let matches = searcher
+///     .search_slice(data)
+///     .collect::<Result<Vec<_>, _>>()?;
+/// assert_eq!(matches.len(), 1);
+/// assert_eq!(matches[0].offset, 2);
+/// assert_eq!(matches[0].capture(1), Some(&[0x80, 0x01][..]));
+/// # Ok::<(), binarygrep::BgrepError>(())
+/// ```
+pub struct Searcher {
+    search: Box<dyn Search>,
+    context: usize,
+    no_overlap: bool,
+}
+
+impl Searcher {
+    pub fn builder() -> SearcherBuilder {
+        SearcherBuilder::default()
+    }
+
+    /// Iterate over the matches in `reader`
+    pub fn search_reader<R: Read>(&self, reader: R) -> Matches<'_, R> {
+        self.matches(reader, self.reader_buffer_size())
+    }
+
+    /// Pass the matches in `reader` to `sink` until it stops the search
+    pub fn search_sink<R: Read>(
+        &self,
+        mut reader: R,
+        sink: &mut impl Sink,
+    ) -> Result<(), BgrepError> {
+        self.search_fd(&mut reader, 0, sink)
+    }
+
+    /// Iterate over the matches in `data`
+    pub fn search_slice<'a>(&'a self, data: &'a [u8]) -> Matches<'a, &'a [u8]> {
+        // A small input does not need a buffer of the default size
+        let size = cmp::max(
+            cmp::min(data.len(), BUFFER_SIZE),
+            cmp::max(self.max_pattern_len() + self.context, 1),
+        );
+        self.matches(data, size)
+    }
+
+    /// Iterate over the matches in the file `path`
+    pub fn search_path(&self, path: impl AsRef<Path>) -> Result<Matches<'_, fs::File>, BgrepError> {
+        let path = path.as_ref();
+        let f = fs::File::open(path).map_err(|err| {
+            BgrepError(format!(
+                "Cannot open file '{}': {}",
+                path.to_string_lossy(),
+                err
+            ))
+        })?;
+        Ok(self.search_reader(f))
+    }
+
+    /// Number of captures of the pattern
+    pub fn capture_count(&self) -> usize {
+        self.search.capture_count()
+    }
+
+    /// Whether the captures are groups written explicitly in the pattern
+    pub(crate) fn has_groups(&self) -> bool {
+        self.search.has_groups()
+    }
+
+    /// Size of the buffer for searching a reader of unknown size
+    pub(crate) fn reader_buffer_size(&self) -> usize {
+        buffer_size(self.max_pattern_len(), self.context)
+    }
+
+    pub(crate) fn max_pattern_len(&self) -> usize {
+        self.search.max_pattern_len()
+    }
+
+    /// First match in the current chunk of `data` that starts at the absolute offset `from` or
+    /// later, the shortest one if several start at the same index
+    pub(crate) fn first_match(&self, data: &Buffer, from: usize) -> Option<BufferMatch> {
+        let mut first: Option<BufferMatch> = None;
+        let _ = self.search.search_each(
+            &data.haystack(),
+            from.saturating_sub(data.position),
+            &mut |m| {
+                match &first {
+                    Some(f) if f.start != m.start => return ControlFlow::Break(()),
+                    Some(f) if f.len <= m.len => (),
+                    _ => first = Some(m),
+                }
+                ControlFlow::Continue(())
+            },
+        );
+        first
+    }
+
+    /// Search `f`, whose first byte is at absolute offset `start`, and pass the matches to
+    /// `sink`
+    pub(crate) fn search_fd(
+        &self,
+        f: &mut impl Read,
+        start: usize,
+        sink: &mut dyn Sink,
+    ) -> Result<(), BgrepError> {
+        let mut buffer = Buffer::new(buffer_size(self.max_pattern_len(), self.context), start);
+        let mut last_end = start;
+        loop {
+            buffer.read(f).map_err(read_error)?;
+            let flow = self.search_chunk(&buffer, &mut last_end, &mut |m| {
+                let ctx = Context::new(&buffer, &m, self.context);
+                match Match::new(&buffer, m) {
+                    Some(m) => sink.matched(&m, &ctx),
+                    None => ControlFlow::Continue(()),
+                }
+            });
+            if flow.is_break() || self.is_last_chunk(&buffer) {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn matches<R: Read>(&self, reader: R, buffer_size: usize) -> Matches<'_, R> {
+        Matches {
+            searcher: self,
+            reader,
+            state: MatchesState::new(buffer_size),
+        }
+    }
+
+    /// Search the current chunk of `buffer`
+    ///
+    /// `last_end` is the absolute offset of the end of the last match, matches of the next chunk
+    /// may extend into it unless overlapping matches are suppressed.
+    fn search_chunk(
+        &self,
+        buffer: &Buffer,
+        last_end: &mut usize,
+        found: &mut dyn FnMut(BufferMatch) -> ControlFlow<()>,
+    ) -> ControlFlow<()> {
+        let offset = if self.no_overlap {
+            last_end.saturating_sub(buffer.position)
+        } else {
+            0
+        };
+        self.search
+            .search_each(&buffer.haystack(), offset, &mut |m| {
+                *last_end = cmp::max(*last_end, buffer.position + m.start + m.len);
+                found(m)
+            })
+    }
+
+    fn is_last_chunk(&self, buffer: &Buffer) -> bool {
+        // Further chunks cannot contain matches of a pattern anchored at the start
+        buffer.is_eof() || self.search.is_anchored_at_start()
+    }
+}
+
+/// Iterator over the matches in an input
+pub struct Matches<'a, R: Read> {
+    searcher: &'a Searcher,
+    reader: R,
+    state: MatchesState,
+}
+
+impl<R: Read> Iterator for Matches<'_, R> {
+    type Item = Result<Match, BgrepError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(m) = self.state.pending.pop_front() {
+                return Some(m);
+            }
+            if self.state.done {
+                return None;
+            }
+            let read = self.state.buffer.read(&mut self.reader);
+            self.state.search_chunk(self.searcher, read);
+        }
+    }
+}
+
+/// State of an iteration over the matches in an input, which is independent of how the input is
+/// read
+pub(crate) struct MatchesState {
+    pub(crate) buffer: Buffer,
+    /// Matches of the current chunk that have not been returned yet
+    pub(crate) pending: VecDeque<Result<Match, BgrepError>>,
+    last_end: usize,
+    /// Whether no further chunks need to be read
+    pub(crate) done: bool,
+}
+
+impl MatchesState {
+    pub(crate) fn new(buffer_size: usize) -> MatchesState {
+        MatchesState {
+            buffer: Buffer::new(buffer_size, 0),
+            pending: VecDeque::new(),
+            last_end: 0,
+            done: false,
+        }
+    }
+
+    /// Search the chunk that has been read into `buffer`, `read` is the result of reading it
+    pub(crate) fn search_chunk(&mut self, searcher: &Searcher, read: std::io::Result<usize>) {
+        if let Err(err) = read {
+            self.pending.push_back(Err(read_error(err)));
+            self.done = true;
+            return;
+        }
+        let buffer = &self.buffer;
+        let pending = &mut self.pending;
+        let _ = searcher.search_chunk(buffer, &mut self.last_end, &mut |m| {
+            pending.extend(Match::new(buffer, m).map(Ok));
+            ControlFlow::Continue(())
+        });
+        self.done = searcher.is_last_chunk(buffer);
+    }
+}
+
+/// Size of a buffer for patterns of up to `max_pattern_len` bytes that keeps `context` bytes
+/// before and after each match
+pub(crate) fn buffer_size(max_pattern_len: usize, context: usize) -> usize {
+    cmp::max(BUFFER_SIZE, max_pattern_len + context)
+}
+
+fn read_error(err: std::io::Error) -> BgrepError {
+    BgrepError(format!("Error while reading: {}", err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn offsets<R: Read>(matches: Matches<R>) -> Vec<usize> {
+        matches.map(|m| m.unwrap().offset).collect()
+    }
+
+    #[test]
+    fn test_search_slice() {
+        let searcher = Searcher::builder().pattern("0102").build().unwrap();
+        let data = [0x01, 0x02, 0x01, 0x02, 0x02, 0x01, 0x02];
+        assert_eq!(offsets(searcher.search_slice(&data)), vec![0, 2, 5]);
+        assert_eq!(offsets(searcher.search_slice(&[])), vec![]);
+        let m = searcher.search_slice(&data).next().unwrap().unwrap();
+        assert_eq!(m.bytes, vec![0x01, 0x02]);
+        assert_eq!(m.capture(1), None);
+    }
+
+    #[test]
+    fn test_search_reader_options() {
+        let data = [0xaa; 10];
+        let searcher = Searcher::builder()
+            .pattern("aa aa")
+            .align(3)
+            .align_offset(1)
+            .build()
+            .unwrap();
+        assert_eq!(offsets(searcher.search_reader(&data[..])), vec![1, 4, 7]);
+        let searcher = Searcher::builder()
+            .pattern("aa{2,3}")
+            .extended(true)
+            .no_overlap(true)
+            .build()
+            .unwrap();
+        assert_eq!(offsets(searcher.search_reader(&data[..])), vec![0, 3, 6]);
+        assert!(Searcher::builder().pattern("aa").align(0).build().is_err());
+        assert!(Searcher::builder().pattern("a.").build().is_err());
+    }
+
+    #[test]
+    fn test_captures() {
+        let searcher = Searcher::builder()
+            .pattern("01 (.) 02 (.{1,2}) 03")
+            .extended(true)
+            .build()
+            .unwrap();
+        assert_eq!(searcher.capture_count(), 2);
+        let data = [0x00, 0x01, 0xaa, 0x02, 0xbb, 0xcc, 0x03];
+        let matches: Vec<Match> = searcher.search_slice(&data).map(|m| m.unwrap()).collect();
+        assert_eq!(
+            matches,
+            vec![Match {
+                offset: 1,
+                len: 6,
+                bytes: vec![0x01, 0xaa, 0x02, 0xbb, 0xcc, 0x03],
+                captures: vec![1..2, 3..5],
+            }]
+        );
+        assert_eq!(matches[0].capture(2), Some(&[0xbb, 0xcc][..]));
+        assert_eq!(matches[0].capture(0), None);
+    }
+
+    #[test]
+    fn test_search_sink() {
+        let searcher = Searcher::builder()
+            .pattern("00")
+            .context(2)
+            .build()
+            .unwrap();
+        let data = [0x01, 0x02, 0x00, 0x03, 0x00, 0x00, 0x04];
+        let mut found = vec![];
+        searcher
+            .search_sink(&data[..], &mut |m: &Match, ctx: &Context| {
+                found.push((m.offset, ctx.before(4), ctx.after(1)));
+                ControlFlow::Continue(())
+            })
+            .unwrap();
+        assert_eq!(
+            found,
+            vec![
+                (2, vec![0x01, 0x02], vec![0x03]),
+                (4, vec![0x00, 0x03], vec![0x00]),
+                (5, vec![0x03, 0x00], vec![0x04]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_search_sink_break() {
+        let searcher = Searcher::builder().pattern("00").build().unwrap();
+        // Many more matches than the sink accepts
+        let data = vec![0; 3 * BUFFER_SIZE];
+        let mut count = 0;
+        searcher
+            .search_sink(&data[..], &mut |_: &Match, _: &Context| {
+                count += 1;
+                if count == 10 {
+                    ControlFlow::Break(())
+                } else {
+                    ControlFlow::Continue(())
+                }
+            })
+            .unwrap();
+        assert_eq!(count, 10);
+    }
+
+    /// Search `data` in chunks of `size` bytes and return the absolute offsets and lengths
+    fn search_chunks(pattern: &str, data: &[u8], size: usize) -> Vec<(usize, usize)> {
+        let extendedsearch = ExtendedSearch::new(pattern, SearchOptions::default()).unwrap();
+        let mut buffer = Buffer::new(size, 0);
+        let mut f = data;
+        let mut result = vec![];
+        loop {
+            buffer.read(&mut f).unwrap();
+            for m in extendedsearch.search(&buffer.haystack(), 0) {
+                result.push((buffer.position + m.start, m.len));
+            }
+            if buffer.is_eof() {
+                return result;
+            }
+        }
+    }
+
+    #[test]
+    fn test_chunks() {
+        let data = [0x7f, 0x45, 0x7f, 0x45, 0x00, 0x7f, 0x45, 0x00, 0x7f, 0x45];
+        assert_eq!(search_chunks("7f 45", &data, 2).len(), 4);
+        assert_eq!(search_chunks("45 00 7f", &data, 2), vec![(3, 3), (6, 3)]);
+        assert_eq!(search_chunks("^7f 45", &data, 2), vec![(0, 2)]);
+        assert_eq!(search_chunks(" ^ 7f 45 $ ", &data, 2), vec![]);
+        assert_eq!(search_chunks("7f 45$", &data, 2), vec![(8, 2)]);
+        assert_eq!(search_chunks("7f 45$", &data, 3), vec![(8, 2)]);
+        assert_eq!(search_chunks("7f 45$", &data, 16), vec![(8, 2)]);
+        assert_eq!(search_chunks("^.{1,20}$", &data, 16), vec![(0, 10)]);
+        assert_eq!(search_chunks("(7f) .{0,3} \\1 45$", &data, 8), vec![(5, 5)]);
+    }
+
+    #[test]
+    fn test_search_path() {
+        let searcher = Searcher::builder().pattern("c3df").build().unwrap();
+        assert_eq!(
+            offsets(searcher.search_path("tests/testdata_783").unwrap()),
+            vec![0x256]
+        );
+        assert!(searcher.search_path("tests/does_not_exist").is_err());
+    }
+}
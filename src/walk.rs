@@ -0,0 +1,525 @@
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use binarygrep_core::BgrepError;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use ignore::Match;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+
+/// Files with gitignore rules for the directory they are in, later ones take precedence
+const IGNORE_FILES: [&str; 3] = [".gitignore", ".ignore", ".bgrepignore"];
+
+/// Symbolic links followed by a recursive search
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum Follow {
+    /// Only links given on the command line
+    #[default]
+    CommandLine,
+    All,
+    Never,
+}
+
+/// Handling of devices, FIFOs and sockets
+///
+/// FIFOs and sockets found by a recursive search are skipped in any case, reading them may block.
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum Devices {
+    Read,
+    Skip,
+}
+
+/// Order in which the entries of a directory are visited
+///
+/// Sizes and modification times are sorted in ascending order, `None` keeps the order in which
+/// the file system returns the entries.
+#[derive(Clone, Copy, Default, PartialEq, clap::ValueEnum)]
+pub enum Sort {
+    #[default]
+    Path,
+    Size,
+    Mtime,
+    None,
+}
+
+/// Kind of a file that is neither a regular file nor a directory
+#[derive(PartialEq)]
+enum Special {
+    BlockDevice,
+    CharDevice,
+    /// FIFO or socket, reading may block forever
+    Pipe,
+}
+
+/// Selection of the files and directories visited by a recursive search
+#[derive(Default)]
+pub struct Walk {
+    /// Only search files whose name matches, all files if empty
+    pub include: GlobSet,
+    /// Skip files whose name matches
+    pub exclude: GlobSet,
+    /// Skip directories whose name matches
+    pub exclude_dir: GlobSet,
+    /// Number of directory levels below the directories given on the command line that are
+    /// entered, 0 only searches the files directly in them
+    pub max_depth: Option<usize>,
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+    /// Skip files and directories whose name starts with a dot
+    pub skip_hidden: bool,
+    /// Skip files and directories matched by the rules of ignore files
+    pub ignore_files: bool,
+    pub follow: Follow,
+    /// Skip directories on other file systems than the directory given on the command line
+    pub one_file_system: bool,
+    /// Handling of special files, by default only those given on the command line are read
+    pub devices: Option<Devices>,
+    pub sort: Sort,
+    /// Device and inode numbers of the directories entered so far
+    pub visited: RefCell<HashSet<(u64, u64)>>,
+}
+
+/// Directory visited by a recursive search
+pub struct Dir<'a> {
+    parent: Option<&'a Dir<'a>>,
+    /// Number of directory levels below a directory given on the command line
+    depth: usize,
+    /// Rules of the ignore files in the directory
+    ignore: Gitignore,
+    /// Device number of the directory given on the command line
+    device: Option<u64>,
+    /// Device and inode number of the directory
+    id: Option<(u64, u64)>,
+    /// Whether the directory has been reached through a symbolic link found by the search
+    via_link: bool,
+}
+
+/// Compile `globs`, which are matched against file names
+pub fn glob_set(globs: &[String]) -> Result<GlobSet, BgrepError> {
+    let mut builder = GlobSetBuilder::new();
+    for glob in globs {
+        builder.add(
+            Glob::new(glob)
+                .map_err(|err| BgrepError(format!("Invalid glob '{}': {}", glob, err)))?,
+        );
+    }
+    builder
+        .build()
+        .map_err(|err| BgrepError(format!("Invalid glob: {}", err)))
+}
+
+impl Walk {
+    /// Enter the directory `path`, which is an entry of `parent` or given on the command line
+    ///
+    /// Returns `None` if the entries of the directory are not visited, which includes directories
+    /// that contain themselves, e.g. through a symbolic link to a parent directory, and
+    /// directories reached through a symbolic link that have been entered before.  Directories
+    /// given on the command line more than once are searched each time.
+    pub fn enter<'a>(
+        &self,
+        path: &Path,
+        parent: Option<&'a Dir<'a>>,
+    ) -> Result<Option<Dir<'a>>, BgrepError> {
+        let depth = parent.map_or(0, |parent| parent.depth + 1);
+        if self.max_depth.is_some_and(|max_depth| depth > max_depth) {
+            return Ok(None);
+        }
+        let metadata = fs::metadata(path).map_err(|err| {
+            BgrepError(format!(
+                "Cannot read metadata of '{}': {}",
+                path.display(),
+                err
+            ))
+        })?;
+        let id = file_id(&metadata);
+        let device = match parent {
+            Some(parent) => parent.device,
+            None => id.map(|(device, _)| device),
+        };
+        if self.one_file_system && id.map(|(device, _)| device) != device {
+            return Ok(None);
+        }
+        if id.is_some() && ancestors(parent).any(|ancestor| ancestor.id == id) {
+            return Ok(None);
+        }
+        let via_link = parent.is_some_and(|parent| parent.via_link || path.is_symlink());
+        if let Some(id) = id
+            && !self.visited.borrow_mut().insert(id)
+            && via_link
+        {
+            return Ok(None);
+        }
+        let ignore = if self.ignore_files {
+            read_ignore_files(path)?
+        } else {
+            Gitignore::empty()
+        };
+        Ok(Some(Dir {
+            parent,
+            depth,
+            ignore,
+            device,
+            id,
+            via_link,
+        }))
+    }
+
+    /// Whether `path` is visited if it is a symbolic link
+    pub fn follows(&self, path: &Path, command_line: bool) -> bool {
+        match self.follow {
+            Follow::All => true,
+            Follow::CommandLine if command_line => true,
+            _ => !path.is_symlink(),
+        }
+    }
+
+    /// Sort the `entries` of a directory into the order in which they are visited
+    ///
+    /// Entries whose metadata cannot be read are visited first.
+    pub fn sort(&self, entries: &mut [PathBuf]) {
+        match self.sort {
+            Sort::Path => entries.sort(),
+            Sort::Size => {
+                entries.sort_by_cached_key(|path| fs::metadata(path).map(|m| m.len()).ok())
+            }
+            Sort::Mtime => entries
+                .sort_by_cached_key(|path| fs::metadata(path).and_then(|m| m.modified()).ok()),
+            Sort::None => (),
+        }
+    }
+
+    /// Whether the entry `path` of the directory `dir` is visited
+    pub fn visit(&self, path: &Path, is_dir: bool, dir: &Dir) -> bool {
+        let Some(name) = path.file_name() else {
+            return true;
+        };
+        if self.skip_hidden && name.as_encoded_bytes().starts_with(b".") {
+            return false;
+        }
+        let visit = if is_dir {
+            !self.exclude_dir.is_match(name)
+        } else {
+            (self.include.is_empty() || self.include.is_match(name)) && !self.exclude.is_match(name)
+        };
+        visit && !dir.is_ignored(path, is_dir)
+    }
+
+    /// Whether the file `path`, which is not a directory, is searched
+    ///
+    /// Checks the kind of the file and whether its size is within the limits.
+    pub fn accepts(&self, path: &Path, command_line: bool) -> Result<bool, BgrepError> {
+        let metadata_error = |err: io::Error| {
+            BgrepError(format!(
+                "Cannot read metadata of '{}': {}",
+                path.display(),
+                err
+            ))
+        };
+        let metadata = fs::metadata(path)
+            .map_err(|err| BgrepError(format!("Cannot open file '{}': {}", path.display(), err)))?;
+        let special = special(&metadata.file_type());
+        if let Some(special) = &special {
+            let read = match self.devices {
+                Some(Devices::Read) => command_line || *special != Special::Pipe,
+                Some(Devices::Skip) => false,
+                None => command_line,
+            };
+            if !read {
+                return Ok(false);
+            }
+        }
+        if self.min_size.is_none() && self.max_size.is_none() {
+            return Ok(true);
+        }
+        let size = match special {
+            Some(Special::BlockDevice) => fs::File::open(path)
+                .and_then(|mut f| file_size(&mut f))
+                .map_err(metadata_error)?,
+            _ => metadata.len(),
+        };
+        Ok(self.min_size.is_none_or(|min_size| size >= min_size)
+            && self.max_size.is_none_or(|max_size| size <= max_size))
+    }
+}
+
+/// Size of the file `f`, block devices report the size of the device
+///
+/// The position of `f` is at its start afterwards.
+pub fn file_size(f: &mut fs::File) -> io::Result<u64> {
+    let metadata = f.metadata()?;
+    if special(&metadata.file_type()) == Some(Special::BlockDevice) {
+        let size = f.seek(SeekFrom::End(0))?;
+        f.rewind()?;
+        Ok(size)
+    } else {
+        Ok(metadata.len())
+    }
+}
+
+#[cfg(unix)]
+fn special(file_type: &fs::FileType) -> Option<Special> {
+    use std::os::unix::fs::FileTypeExt;
+    if file_type.is_block_device() {
+        Some(Special::BlockDevice)
+    } else if file_type.is_char_device() {
+        Some(Special::CharDevice)
+    } else if file_type.is_fifo() || file_type.is_socket() {
+        Some(Special::Pipe)
+    } else {
+        None
+    }
+}
+
+#[cfg(not(unix))]
+fn special(_file_type: &fs::FileType) -> Option<Special> {
+    None
+}
+
+impl Dir<'_> {
+    /// Whether `path` is ignored by the rules of this directory or its parents
+    ///
+    /// The rules of a directory take precedence over those of its parents.
+    fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        let mut dir = Some(self);
+        while let Some(current) = dir {
+            match current.ignore.matched(path, is_dir) {
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => return false,
+                Match::None => dir = current.parent,
+            }
+        }
+        false
+    }
+}
+
+/// Device and inode number of a file
+#[cfg(unix)]
+fn file_id(metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+/// `dir` and the directories it is in
+fn ancestors<'a>(dir: Option<&'a Dir<'a>>) -> impl Iterator<Item = &'a Dir<'a>> {
+    std::iter::successors(dir, |dir| dir.parent)
+}
+
+#[cfg(not(unix))]
+fn file_id(_metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// Read the rules of the ignore files in the directory `path`
+fn read_ignore_files(path: &Path) -> Result<Gitignore, BgrepError> {
+    let mut builder = GitignoreBuilder::new(path);
+    for name in IGNORE_FILES {
+        let file = path.join(name);
+        if file.is_file()
+            && let Some(err) = builder.add(&file)
+        {
+            return Err(BgrepError(format!(
+                "Invalid ignore file '{}': {}",
+                file.display(),
+                err
+            )));
+        }
+    }
+    builder
+        .build()
+        .map_err(|err| BgrepError(format!("Invalid ignore file: {}", err)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn globs(globs: &[&str]) -> GlobSet {
+        glob_set(&globs.iter().map(|g| g.to_string()).collect::<Vec<_>>()).unwrap()
+    }
+
+    #[test]
+    fn test_visit() {
+        let dir = Walk::default()
+            .enter(Path::new("tests"), None)
+            .unwrap()
+            .unwrap();
+        let walk = Walk {
+            include: globs(&["*.bin", "*.img"]),
+            exclude: globs(&["skip*"]),
+            exclude_dir: globs(&["build"]),
+            ..Default::default()
+        };
+        assert!(walk.visit(Path::new("tests/a.bin"), false, &dir));
+        assert!(walk.visit(Path::new("tests/a.img"), false, &dir));
+        assert!(!walk.visit(Path::new("tests/a.txt"), false, &dir));
+        assert!(!walk.visit(Path::new("tests/skip.bin"), false, &dir));
+        assert!(walk.visit(Path::new("tests/.hidden.bin"), false, &dir));
+        // Include and exclude globs only apply to files
+        assert!(walk.visit(Path::new("tests/sub"), true, &dir));
+        assert!(walk.visit(Path::new("tests/skip"), true, &dir));
+        assert!(!walk.visit(Path::new("tests/build"), true, &dir));
+        let walk = Walk {
+            skip_hidden: true,
+            ..Default::default()
+        };
+        assert!(walk.visit(Path::new("tests/a.txt"), false, &dir));
+        assert!(!walk.visit(Path::new("tests/.hidden"), false, &dir));
+        assert!(!walk.visit(Path::new("tests/.git"), true, &dir));
+        assert!(glob_set(&["[".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_enter() {
+        let path = Path::new("tests");
+        let dir = Walk::default().enter(path, None).unwrap().unwrap();
+        assert_eq!(dir.depth, 0);
+        let dir = Walk::default()
+            .enter(Path::new("tests/subdir"), Some(&dir))
+            .unwrap()
+            .unwrap();
+        assert_eq!(dir.depth, 1);
+        let walk = Walk {
+            max_depth: Some(1),
+            ..Default::default()
+        };
+        let dir = walk.enter(Path::new("."), None).unwrap().unwrap();
+        let dir = walk.enter(path, Some(&dir)).unwrap().unwrap();
+        assert!(
+            walk.enter(Path::new("tests/subdir"), Some(&dir))
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_sort() {
+        let mut entries = vec![
+            PathBuf::from("tests/testdata_783"),
+            PathBuf::from("tests/subdir/testdata_1200"),
+            PathBuf::from("tests/integration_tests.rs"),
+        ];
+        let walk = Walk::default();
+        walk.sort(&mut entries);
+        assert_eq!(
+            entries,
+            [
+                Path::new("tests/integration_tests.rs"),
+                Path::new("tests/subdir/testdata_1200"),
+                Path::new("tests/testdata_783")
+            ]
+        );
+        let walk = Walk {
+            sort: Sort::Size,
+            ..Default::default()
+        };
+        entries.push(PathBuf::from("tests/missing"));
+        walk.sort(&mut entries);
+        assert_eq!(
+            entries[..3],
+            [
+                Path::new("tests/missing"),
+                Path::new("tests/testdata_783"),
+                Path::new("tests/subdir/testdata_1200")
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_follow() {
+        let root =
+            std::env::temp_dir().join(format!("binarygrep_test_follow_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("sub")).unwrap();
+        std::os::unix::fs::symlink("..", root.join("sub/parent")).unwrap();
+        let walk = Walk::default();
+        assert!(walk.follows(&root.join("sub"), false));
+        assert!(!walk.follows(&root.join("sub/parent"), false));
+        assert!(walk.follows(&root.join("sub/parent"), true));
+        let walk = Walk {
+            follow: Follow::Never,
+            ..Default::default()
+        };
+        assert!(!walk.follows(&root.join("sub/parent"), true));
+        let walk = Walk {
+            follow: Follow::All,
+            ..Default::default()
+        };
+        assert!(walk.follows(&root.join("sub/parent"), false));
+        // The link leads back to the directory entered first
+        let dir = walk.enter(&root, None).unwrap().unwrap();
+        let sub = walk.enter(&root.join("sub"), Some(&dir)).unwrap().unwrap();
+        assert!(
+            walk.enter(&root.join("sub/parent"), Some(&sub))
+                .unwrap()
+                .is_none()
+        );
+        // Directories entered before are only skipped if they are reached through a link
+        assert!(walk.enter(&root.join("sub"), None).unwrap().is_some());
+        fs::create_dir(root.join("other")).unwrap();
+        std::os::unix::fs::symlink("../sub", root.join("other/link")).unwrap();
+        let other = walk
+            .enter(&root.join("other"), Some(&dir))
+            .unwrap()
+            .unwrap();
+        assert!(
+            walk.enter(&root.join("other/link"), Some(&other))
+                .unwrap()
+                .is_none()
+        );
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_ignore_files() {
+        let root = std::env::temp_dir().join("binarygrep_walk_test_ignore_files");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::write(root.join(".gitignore"), "*.log\nbuild/\n").unwrap();
+        fs::write(root.join(".bgrepignore"), "!keep.log\n").unwrap();
+        fs::write(root.join("sub/.ignore"), "!sub.log\n*.tmp\n").unwrap();
+        let walk = Walk {
+            ignore_files: true,
+            ..Default::default()
+        };
+        let dir = walk.enter(&root, None).unwrap().unwrap();
+        assert!(walk.visit(&root.join("a.bin"), false, &dir));
+        assert!(!walk.visit(&root.join("a.log"), false, &dir));
+        assert!(walk.visit(&root.join("keep.log"), false, &dir));
+        assert!(!walk.visit(&root.join("build"), true, &dir));
+        assert!(walk.visit(&root.join("build"), false, &dir));
+        let sub = walk.enter(&root.join("sub"), Some(&dir)).unwrap().unwrap();
+        assert!(!walk.visit(&root.join("sub/a.log"), false, &sub));
+        assert!(walk.visit(&root.join("sub/sub.log"), false, &sub));
+        assert!(!walk.visit(&root.join("sub/a.tmp"), false, &sub));
+        assert!(walk.visit(&root.join("a.tmp"), false, &dir));
+        let walk = Walk::default();
+        let dir = walk.enter(&root, None).unwrap().unwrap();
+        assert!(walk.visit(&root.join("a.log"), false, &dir));
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_accepts() {
+        let path = Path::new("tests/testdata_783");
+        assert!(Walk::default().accepts(path, false).unwrap());
+        let walk = Walk {
+            min_size: Some(783),
+            max_size: Some(783),
+            ..Default::default()
+        };
+        assert!(walk.accepts(path, false).unwrap());
+        let walk = Walk {
+            max_size: Some(782),
+            ..Default::default()
+        };
+        assert!(!walk.accepts(path, false).unwrap());
+        let walk = Walk {
+            min_size: Some(784),
+            ..Default::default()
+        };
+        assert!(!walk.accepts(path, false).unwrap());
+    }
+}
@@ -1,5 +1,3 @@
-use assert_cmd;
-
 #[test]
 fn test_firstbytes() -> Result<(), Box<dyn std::error::Error>> {
     let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
@@ -174,11 +172,23 @@ fn test_quantifier() -> Result<(), Box<dyn std::error::Error>> {
         .arg("--extended")
         .arg("05{3,6}")
         .arg("tests/testdata_10485760");
-    let output = "0076a138: 050505\n00a00000: 050505\n00a00001: 050505\n00a00002: 050505\n00a00000: 05050505\n00a00001: 05050505\n00a00000: 0505050505\n";
+    let output = "0076a138: 050505\n00a00000: 050505\n00a00000: 05050505\n00a00000: 0505050505\n00a00001: 050505\n00a00001: 05050505\n00a00002: 050505\n";
     cmd.assert().success().stdout(output);
     Ok(())
 }
 
+#[test]
+fn test_quantifier_order() -> Result<(), Box<dyn std::error::Error>> {
+    // Matches are reported by offset, shorter ones first at the same offset
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+    cmd.args(["--no-ascii", "--extended", "05{3,6}"])
+        .write_stdin(&[0x00, 0x05, 0x05, 0x05, 0x05, 0x05, 0x00][..]);
+    cmd.assert().success().stdout(
+        "00000001: 050505\n00000001: 05050505\n00000001: 0505050505\n00000002: 050505\n00000002: 05050505\n00000003: 050505\n",
+    );
+    Ok(())
+}
+
 #[test]
 fn test_wildcard_with_quantifier() -> Result<(), Box<dyn std::error::Error>> {
     let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
@@ -274,11 +284,9 @@ fn test_no_offset() -> Result<(), Box<dyn std::error::Error>> {
 fn test_recursive() -> Result<(), Box<dyn std::error::Error>> {
     let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
     cmd.arg("--recursive").arg("deede4c1").arg("tests");
-    let output = cmd.assert().success().get_output().stdout.clone();
-    // order of output is not deterministic for multiple input files
-    let expected1: &[u8] = b"tests/subdir/testdata_1200 000002d1: deede4c1  ....\ntests/testdata_4194304 0000d250: deede4c1  ....\n";
-    let expected2: &[u8] = b"tests/testdata_4194304 0000d250: deede4c1  ....\ntests/subdir/testdata_1200 000002d1: deede4c1  ....\n";
-    assert!(output == expected1 || output == expected2);
+    cmd.assert().success().stdout(
+        "tests/subdir/testdata_1200 000002d1: deede4c1  ....\ntests/testdata_4194304 0000d250: deede4c1  ....\n",
+    );
     Ok(())
 }
 
@@ -303,3 +311,1103 @@ fn test_int_overflow() -> Result<(), Box<dyn std::error::Error>> {
     cmd.assert().success().stdout("00000000: b887\n");
     Ok(())
 }
+
+#[test]
+fn test_skip() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+    cmd.arg("--no-ascii")
+        .arg("--skip")
+        .arg("0x256")
+        .arg("c3df")
+        .arg("tests/testdata_783");
+    cmd.assert().success().stdout("00000256: c3df\n");
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+    cmd.arg("--no-ascii")
+        .arg("--start")
+        .arg("599")
+        .arg("c3df")
+        .arg("tests/testdata_783");
+    cmd.assert().success().stdout("");
+    Ok(())
+}
+
+#[test]
+fn test_skip_from_end() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+    cmd.arg("--no-ascii")
+        .arg("-B2")
+        .arg("--skip=-12")
+        .arg("--extended")
+        .arg("2e47")
+        .arg("tests/testdata_783");
+    // context before the skipped region is not printed
+    cmd.assert().success().stdout("00000304: 0f2e47\n");
+    Ok(())
+}
+
+#[test]
+fn test_length() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+    cmd.arg("--no-ascii")
+        .arg("-A2")
+        .arg("--length")
+        .arg("0x258")
+        .arg("c3df")
+        .arg("tests/testdata_783");
+    cmd.assert().success().stdout("00000256: c3df\n");
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+    cmd.arg("--no-ascii")
+        .arg("--skip")
+        .arg("0x200")
+        .arg("--length")
+        .arg("0x57")
+        .arg("c3df")
+        .arg("tests/testdata_783");
+    cmd.assert().success().stdout("");
+    Ok(())
+}
+
+#[test]
+fn test_end() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+    cmd.arg("--no-ascii")
+        .arg("--end=-0xb7")
+        .arg("c3df")
+        .arg("tests/testdata_783");
+    cmd.assert().success().stdout("00000256: c3df\n");
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+    cmd.arg("--no-ascii")
+        .arg("--end=-0xb8")
+        .arg("c3df")
+        .arg("tests/testdata_783");
+    cmd.assert().success().stdout("");
+    Ok(())
+}
+
+#[test]
+fn test_skip_stdin() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+    cmd.arg("--no-ascii")
+        .arg("--skip")
+        .arg("0x250")
+        .arg("--length")
+        .arg("8")
+        .arg("c3df")
+        .pipe_stdin("tests/testdata_783")?;
+    cmd.assert().success().stdout("00000256: c3df\n");
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+    cmd.arg("--skip=-16")
+        .arg("c3df")
+        .pipe_stdin("tests/testdata_783")?;
+    cmd.assert().failure();
+    Ok(())
+}
+
+#[test]
+fn test_align() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+    cmd.arg("--no-ascii")
+        .arg("--align")
+        .arg("2")
+        .arg("05")
+        .arg("tests/testdata_783");
+    let output = "000000b0: 05\n00000192: 05\n000001a0: 05\n0000028e: 05\n";
+    cmd.assert().success().stdout(output);
+    cmd.arg("--extended");
+    cmd.assert().success().stdout(output);
+    Ok(())
+}
+
+#[test]
+fn test_align_offset() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+    cmd.arg("--no-ascii")
+        .arg("--align")
+        .arg("4")
+        .arg("--align-offset")
+        .arg("2")
+        .arg("--skip")
+        .arg("1")
+        .arg("05")
+        .arg("tests/testdata_783");
+    let output = "00000192: 05\n0000028e: 05\n";
+    cmd.assert().success().stdout(output);
+    cmd.arg("--extended");
+    cmd.assert().success().stdout(output);
+    Ok(())
+}
+
+#[test]
+fn test_align_wildcard() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+    cmd.arg("--no-ascii")
+        .arg("--extended")
+        .arg("--align")
+        .arg("256")
+        .arg(".")
+        .arg("tests/testdata_783");
+    cmd.assert()
+        .success()
+        .stdout("00000000: b8\n00000100: fa\n00000200: f7\n00000300: 79\n");
+    Ok(())
+}
+
+#[test]
+fn test_align_zero() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+    cmd.arg("--align")
+        .arg("0")
+        .arg("05")
+        .arg("tests/testdata_783");
+    cmd.assert().failure();
+    Ok(())
+}
+
+#[test]
+fn test_no_overlap() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+    cmd.arg("--no-ascii")
+        .arg("--no-overlap")
+        .arg("0000")
+        .write_stdin(b"\x00\x00\x00\x00\x00\x01\x00\x00\x00");
+    let output = "00000000: 0000\n00000002: 0000\n00000006: 0000\n";
+    cmd.assert().success().stdout(output);
+    cmd.arg("--extended");
+    cmd.assert().success().stdout(output);
+    Ok(())
+}
+
+#[test]
+fn test_no_overlap_quantifier() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+    cmd.arg("--no-ascii")
+        .arg("--no-overlap")
+        .arg("--extended")
+        .arg("00{2,3}")
+        .write_stdin(b"\x00\x00\x00\x00\x00\x01\x00\x00\x00");
+    cmd.assert()
+        .success()
+        .stdout("00000000: 000000\n00000003: 0000\n00000006: 000000\n");
+    Ok(())
+}
+
+#[test]
+fn test_no_overlap_buffer_boundary() -> Result<(), Box<dyn std::error::Error>> {
+    let mut data = vec![0x11; 4 * 1024 * 1024 + 16];
+    data[0x3fffff..0x400004].fill(0);
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+    cmd.arg("--no-ascii").arg("0000").write_stdin(data.clone());
+    let output = "003fffff: 0000\n00400000: 0000\n00400001: 0000\n00400002: 0000\n";
+    cmd.assert().success().stdout(output);
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+    cmd.arg("--no-ascii")
+        .arg("--no-overlap")
+        .arg("0000")
+        .write_stdin(data);
+    let output = "003fffff: 0000\n00400001: 0000\n";
+    cmd.assert().success().stdout(output);
+    cmd.arg("--extended");
+    cmd.assert().success().stdout(output);
+    Ok(())
+}
+
+#[test]
+fn test_extract_stdout() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+    cmd.arg("--extract-stdout")
+        .arg("-A1")
+        .arg("05")
+        .arg("tests/testdata_783");
+    cmd.assert()
+        .success()
+        .stdout(&b"\x05\xb2\x05\xbd\x05\x52\x05\x8a"[..]);
+    Ok(())
+}
+
+#[test]
+fn test_extract_directory() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = std::env::temp_dir().join("binarygrep_test_extract_directory");
+    let _ = std::fs::remove_dir_all(&dir);
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+    cmd.arg("--no-ascii")
+        .arg("-B2")
+        .arg("--extract")
+        .arg(&dir)
+        .arg("05")
+        .arg("tests/testdata_783");
+    cmd.assert()
+        .success()
+        .stdout("000000b0: 081905\n00000192: 602b05\n000001a0: ea5505\n0000028e: 375905\n");
+    let mut files: Vec<_> = std::fs::read_dir(&dir)?
+        .map(|entry| entry.unwrap().file_name())
+        .collect();
+    files.sort();
+    assert_eq!(
+        files,
+        [
+            "tests_testdata_783_000000b0.bin",
+            "tests_testdata_783_00000192.bin",
+            "tests_testdata_783_000001a0.bin",
+            "tests_testdata_783_0000028e.bin"
+        ]
+    );
+    assert_eq!(
+        std::fs::read(dir.join("tests_testdata_783_00000192.bin"))?,
+        b"\x60\x2b\x05"
+    );
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_carve() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = std::env::temp_dir().join("binarygrep_test_carve");
+    let _ = std::fs::remove_dir_all(&dir);
+    let mut data = vec![0x11; 200];
+    data[10..13].copy_from_slice(b"\xff\xd8\xff");
+    data[40..42].copy_from_slice(b"\xff\xd9");
+    data[50..53].copy_from_slice(b"\xff\xd8\xff");
+    data[60..63].copy_from_slice(b"\xff\xd8\xff");
+    data[90..92].copy_from_slice(b"\xff\xd9");
+    data[150..153].copy_from_slice(b"\xff\xd8\xff");
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+    cmd.arg("--no-ascii")
+        .arg("--carve")
+        .arg("ffd8ff")
+        .arg("ffd9")
+        .arg("--extract")
+        .arg(&dir)
+        .write_stdin(data.clone());
+    // the match at 0x3c is inside of the region starting at 0x32
+    cmd.assert()
+        .success()
+        .stdout("0000000a: ffd8ff\n00000032: ffd8ff\n00000096: ffd8ff\n");
+    assert_eq!(
+        std::fs::read(dir.join("stdin_0000000a.bin"))?,
+        &data[10..42]
+    );
+    assert_eq!(
+        std::fs::read(dir.join("stdin_00000032.bin"))?,
+        &data[50..92]
+    );
+    // no END till EOF
+    assert_eq!(std::fs::read(dir.join("stdin_00000096.bin"))?, &data[150..]);
+    std::fs::remove_dir_all(&dir)?;
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+    cmd.arg("--carve")
+        .arg("ffd8ff")
+        .arg("ffd9")
+        .arg("--carve-max-size")
+        .arg("20")
+        .arg("--extract-stdout")
+        .write_stdin(data.clone());
+    let expected = [&data[10..30], &data[50..70], &data[150..170]].concat();
+    cmd.assert().success().stdout(expected);
+    Ok(())
+}
+
+#[test]
+fn test_carve_buffer_boundary() -> Result<(), Box<dyn std::error::Error>> {
+    let mut data = vec![0x11; 9 * 1024 * 1024];
+    data[0x3fffff..0x400002].copy_from_slice(b"\xff\xd8\xff");
+    data[0x7fffff..0x800001].copy_from_slice(b"\xff\xd9");
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+    cmd.arg("--carve")
+        .arg("ffd8ff")
+        .arg("ffd9")
+        .arg("--extract-stdout")
+        .write_stdin(data.clone());
+    cmd.assert()
+        .success()
+        .stdout(data[0x3fffff..0x800001].to_vec());
+    Ok(())
+}
+
+#[test]
+fn test_carve_file_argument() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+    cmd.arg("--carve")
+        .arg("7983")
+        .arg("2e47")
+        .arg("--extract-stdout")
+        .arg("tests/testdata_783");
+    cmd.assert()
+        .success()
+        .stdout(&b"\x79\x83\x95\x0f\x2e\x47"[..]);
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+    cmd.arg("--carve")
+        .arg("7983")
+        .arg("2e47")
+        .arg("tests/testdata_783");
+    cmd.assert().failure();
+    Ok(())
+}
+
+#[test]
+fn test_replace_dry_run() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+    cmd.arg("--replace")
+        .arg("0000")
+        .arg("--dry-run")
+        .arg("--extended")
+        .arg("05 .")
+        .arg("tests/testdata_783");
+    cmd.assert().success().stdout(
+        "000000b0: 05b2 -> 0000\n00000192: 05bd -> 0000\n000001a0: 0552 -> 0000\n0000028e: 058a -> 0000\n",
+    );
+    Ok(())
+}
+
+#[test]
+fn test_replace_output() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+    cmd.arg("--replace")
+        .arg("aa")
+        .arg("--output")
+        .arg("-")
+        .arg("0000")
+        .write_stdin(b"\x00\x00\x00\x01\x00\x00\x00\x00".as_slice());
+    // the replacement is shorter than the match
+    cmd.assert().failure().stdout("");
+    cmd.arg("--allow-resize");
+    cmd.assert()
+        .success()
+        .stdout(b"\xaa\x00\x01\xaa\xaa".as_slice());
+    Ok(())
+}
+
+#[test]
+fn test_replace_captures() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+    cmd.arg("--replace")
+        .arg("\\2 ff \\1")
+        .arg("-o")
+        .arg("-")
+        .arg("--extended")
+        .arg("01 .{1,2} 02 [03,04]")
+        .write_stdin(b"\x01\xaa\x02\x03\x01\xbb\xcc\x02\x04\x00".as_slice());
+    // the first replacement is shorter than the match
+    cmd.assert().failure().stdout("");
+    cmd.arg("--allow-resize");
+    cmd.assert()
+        .success()
+        .stdout(b"\x03\xff\xaa\x04\xff\xbb\xcc\x00".as_slice());
+    Ok(())
+}
+
+#[test]
+fn test_replace_in_place() -> Result<(), Box<dyn std::error::Error>> {
+    let file = std::env::temp_dir().join("binarygrep_test_replace_in_place");
+    let backup = std::env::temp_dir().join("binarygrep_test_replace_in_place.orig");
+    let _ = std::fs::remove_file(&backup);
+    let original = std::fs::read("tests/testdata_783")?;
+    std::fs::write(&file, &original)?;
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+    cmd.arg("--replace")
+        .arg("0000")
+        .arg("--in-place")
+        .arg("--backup-suffix")
+        .arg(".orig")
+        .arg("c3df")
+        .arg(&file);
+    cmd.assert().success().stdout("");
+    let mut expected = original.clone();
+    expected[0x256..0x258].fill(0);
+    assert_eq!(std::fs::read(&file)?, expected);
+    assert_eq!(std::fs::read(&backup)?, original);
+    std::fs::remove_file(&file)?;
+    std::fs::remove_file(&backup)?;
+    Ok(())
+}
+
+#[test]
+fn test_replace_in_place_backup() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = std::env::temp_dir().join("binarygrep_test_replace_in_place_backup");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir(&dir)?;
+    let original = std::fs::read("tests/testdata_783")?;
+    std::fs::write(dir.join("a.bin"), [0x01, 0x02, 0x03])?;
+    std::fs::write(dir.join("b.bin"), &original)?;
+    let mut expected = original.clone();
+    expected[0x256..0x258].fill(0);
+    // Files without matches are not rewritten and a second run keeps the backup
+    for _ in 0..2 {
+        let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+        cmd.arg("-r")
+            .arg("--replace")
+            .arg("0000")
+            .arg("--in-place")
+            .arg("c3df")
+            .arg(&dir);
+        cmd.assert().success().stdout("");
+        assert!(!dir.join("a.bin.bak").exists());
+        assert!(!dir.join("b.bin.bak.bak").exists());
+        assert_eq!(std::fs::read(dir.join("b.bin"))?, expected);
+        assert_eq!(std::fs::read(dir.join("b.bin.bak"))?, original);
+    }
+    // An existing backup is not overwritten
+    std::fs::write(dir.join("b.bin"), &original)?;
+    std::fs::write(dir.join("b.bin.bak"), [0x04])?;
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+    cmd.arg("--replace")
+        .arg("0000")
+        .arg("--in-place")
+        .arg("c3df")
+        .arg(dir.join("b.bin"));
+    cmd.assert().failure();
+    assert_eq!(std::fs::read(dir.join("b.bin"))?, original);
+    assert_eq!(std::fs::read(dir.join("b.bin.bak"))?, [0x04]);
+    assert!(!dir.join("b.bin.bgrep-tmp").exists());
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_replace_invalid_capture() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+    cmd.arg("--replace")
+        .arg("\\1")
+        .arg("--dry-run")
+        .arg("c3df")
+        .arg("tests/testdata_783");
+    cmd.assert().failure();
+    Ok(())
+}
+
+#[test]
+fn test_emit_patch_ips() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+    cmd.arg("--replace")
+        .arg("0000")
+        .arg("--emit-patch")
+        .arg("patch.txt")
+        .arg("c3df")
+        .arg("tests/testdata_783");
+    cmd.assert().failure();
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+    let patch = std::env::temp_dir().join("binarygrep_test_emit_patch.ips");
+    cmd.arg("--replace")
+        .arg("0000")
+        .arg("--emit-patch")
+        .arg(&patch)
+        .arg("c3df")
+        .arg("tests/testdata_783");
+    cmd.assert().success().stdout("");
+    assert_eq!(
+        std::fs::read(&patch)?,
+        b"PATCH\x00\x02\x56\x00\x02\x00\x00EOF".as_slice()
+    );
+    std::fs::remove_file(&patch)?;
+    Ok(())
+}
+
+#[test]
+fn test_emit_patch_bps() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+    let patch = std::env::temp_dir().join("binarygrep_test_emit_patch.bps");
+    cmd.arg("--replace")
+        .arg("aa")
+        .arg("--allow-resize")
+        .arg("--emit-patch")
+        .arg(&patch)
+        .arg("0000")
+        .arg("-")
+        .write_stdin(b"\x00\x00\x00\x01\x00\x00\x00\x00".as_slice());
+    cmd.assert().success().stdout("");
+    let written = std::fs::read(&patch)?;
+    std::fs::remove_file(&patch)?;
+    assert_eq!(&written[..7], b"BPS1\x88\x85\x80".as_slice());
+    // TargetRead aa, SourceCopy 00 01 from offset 2, TargetRead aa, TargetRead aa
+    assert_eq!(
+        &written[7..written.len() - 12],
+        b"\x81\xaa\x86\x84\x81\xaa\x81\xaa".as_slice()
+    );
+    Ok(())
+}
+
+#[test]
+fn test_groups() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+    cmd.arg("--extended")
+        .arg("4d5a .{2} (.{4}) (.)")
+        .write_stdin(b"\x00MZ\x00\x00\x80\x00\x00\x00\x01\x02".as_slice());
+    cmd.assert()
+        .success()
+        .stdout("00000001: 4d5a00008000000001  MZ.......  \\1=80000000 \\2=01\n");
+    Ok(())
+}
+
+#[test]
+fn test_groups_decode() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+    cmd.arg("--extended")
+        .arg("--decode")
+        .arg("u32le")
+        .arg("--no-ascii")
+        .arg("4d5a .{2} (.{4}) (.)")
+        .write_stdin(b"\x00MZ\x00\x00\x80\x00\x00\x00\x01\x02".as_slice());
+    // captures of a different size are printed as hex
+    cmd.assert()
+        .success()
+        .stdout("00000001: 4d5a00008000000001  \\1=128 \\2=01\n");
+    Ok(())
+}
+
+#[test]
+fn test_replace_groups() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+    cmd.arg("--replace")
+        .arg("\\2 \\1")
+        .arg("-o")
+        .arg("-")
+        .arg("--extended")
+        .arg("(01 .) (02 .)")
+        .write_stdin(b"\x00\x01\xaa\x02\xbb\x00".as_slice());
+    cmd.assert()
+        .success()
+        .stdout(b"\x00\x02\xbb\x01\xaa\x00".as_slice());
+    Ok(())
+}
+
+#[test]
+fn test_backrefs() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+    cmd.arg("--extended")
+        .arg("(.{2}) ff \\1")
+        .write_stdin(b"\x01\x02\xff\x01\x03\x04\x05\xff\x04\x05\x00".as_slice());
+    cmd.assert()
+        .success()
+        .stdout("00000005: 0405ff0405  .....  \\1=0405\n");
+    Ok(())
+}
+
+#[test]
+fn test_anchor_start() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+    cmd.arg("-x").arg("^b887").arg("tests/testdata_783");
+    cmd.assert().success().stdout("00000000: b887  ..\n");
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+    cmd.arg("-x").arg("^3f30").arg("tests/testdata_783");
+    cmd.assert().success().stdout("");
+    // the start of the searched region
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+    cmd.arg("-x")
+        .arg("--skip")
+        .arg("2")
+        .arg("^3f30")
+        .arg("tests/testdata_783");
+    cmd.assert().success().stdout("00000002: 3f30  ?0\n");
+    Ok(())
+}
+
+#[test]
+fn test_anchor_end() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+    cmd.arg("-x").arg("[d2,5d]$").arg("tests/testdata_783");
+    cmd.assert().success().stdout("0000030e: 5d  ]\n");
+    // the end of the searched region
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+    cmd.arg("-x")
+        .arg("--end")
+        .arg("-1")
+        .arg("[d2,5d]$")
+        .arg("tests/testdata_783");
+    cmd.assert().success().stdout("0000030d: d2  .\n");
+    Ok(())
+}
+
+#[test]
+fn test_fields() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+    cmd.arg("--extended")
+        .arg("--decode")
+        .arg("u16be")
+        .arg("54 (len:u16be) .{len}")
+        .write_stdin(b"\x54\x00\x02AB\x54\x00\x09C".as_slice());
+    cmd.assert()
+        .success()
+        .stdout("00000000: 5400024142  T..AB  \\1=2\n");
+    Ok(())
+}
+
+#[test]
+fn test_recursive_filters() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = std::env::temp_dir().join(format!(
+        "binarygrep_test_recursive_filters_{}",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    for sub in ["build/.git", "build/deep/deeper", "logs"] {
+        std::fs::create_dir_all(dir.join(sub))?;
+    }
+    let data = [0x00, 0xc3, 0xdf, 0x00];
+    for file in [
+        "build/a.bin",
+        "build/a.txt",
+        "build/.hidden.bin",
+        "build/.git/b.bin",
+        "build/deep/c.bin",
+        "build/deep/deeper/d.bin",
+        "logs/e.bin",
+    ] {
+        std::fs::write(dir.join(file), data)?;
+    }
+    std::fs::write(dir.join("build/large.bin"), [0xc3; 100])?;
+    let search = |args: &[&str]| -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+        cmd.current_dir(&dir)
+            .args(["-r", "--no-ascii"])
+            .args(args)
+            .arg("c3df")
+            .arg(".");
+        let output = String::from_utf8(cmd.assert().success().get_output().stdout.clone())?;
+        Ok(output.lines().map(String::from).collect())
+    };
+    assert_eq!(
+        search(&[
+            "--include",
+            "*.bin",
+            "--exclude-dir",
+            "logs",
+            "--skip-hidden",
+            "--max-size",
+            "50"
+        ])?,
+        [
+            "./build/a.bin 00000001: c3df",
+            "./build/deep/c.bin 00000001: c3df",
+            "./build/deep/deeper/d.bin 00000001: c3df"
+        ]
+    );
+    assert_eq!(
+        search(&["--exclude", "*.bin", "--exclude", "*.txt"])?,
+        Vec::<String>::new()
+    );
+    assert_eq!(search(&["--max-depth", "0"])?, Vec::<String>::new());
+    assert_eq!(
+        search(&["--max-depth", "1"])?,
+        [
+            "./build/.hidden.bin 00000001: c3df",
+            "./build/a.bin 00000001: c3df",
+            "./build/a.txt 00000001: c3df",
+            "./logs/e.bin 00000001: c3df"
+        ]
+    );
+    assert_eq!(
+        search(&["--max-depth", "1", "--exclude-dir", "build"])?,
+        ["./logs/e.bin 00000001: c3df"]
+    );
+    assert_eq!(
+        search(&[
+            "--max-depth",
+            "1",
+            "--min-size",
+            "5",
+            "--exclude-dir",
+            ".git"
+        ])?,
+        Vec::<String>::new()
+    );
+    assert_eq!(
+        search(&[
+            "--max-depth",
+            "2",
+            "--include",
+            "*.bin",
+            "--include",
+            "*.txt",
+            "--exclude-dir",
+            "logs"
+        ])?,
+        [
+            "./build/.git/b.bin 00000001: c3df",
+            "./build/.hidden.bin 00000001: c3df",
+            "./build/a.bin 00000001: c3df",
+            "./build/a.txt 00000001: c3df",
+            "./build/deep/c.bin 00000001: c3df"
+        ]
+    );
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_ignore_files() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = std::env::temp_dir().join("binarygrep_test_ignore_files");
+    let _ = std::fs::remove_dir_all(&dir);
+    for sub in ["target/debug", "src/node_modules", "src/gen"] {
+        std::fs::create_dir_all(dir.join(sub))?;
+    }
+    std::fs::write(dir.join(".gitignore"), "target/\n*.o\n")?;
+    std::fs::write(dir.join("src/.ignore"), "node_modules/\n!keep.o\n")?;
+    std::fs::write(dir.join("src/gen/.bgrepignore"), "*\n!*.bin\n")?;
+    let data = [0x00, 0xc3, 0xdf, 0x00];
+    for file in [
+        "a.bin",
+        "a.o",
+        "target/debug/b.bin",
+        "src/c.bin",
+        "src/c.o",
+        "src/keep.o",
+        "src/node_modules/d.bin",
+        "src/gen/e.bin",
+        "src/gen/e.txt",
+    ] {
+        std::fs::write(dir.join(file), data)?;
+    }
+    let search = |args: &[&str]| -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+        cmd.current_dir(&dir)
+            .args(["-r", "--no-ascii", "--no-offset"])
+            .args(args)
+            .arg("c3df")
+            .arg(".");
+        let output = String::from_utf8(cmd.assert().success().get_output().stdout.clone())?;
+        Ok(output.lines().map(String::from).collect())
+    };
+    assert_eq!(
+        search(&[])?,
+        [
+            "./a.bin c3df",
+            "./src/c.bin c3df",
+            "./src/gen/e.bin c3df",
+            "./src/keep.o c3df"
+        ]
+    );
+    assert_eq!(search(&["--no-ignore", "--skip-hidden"])?.len(), 9);
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_symbolic_links() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = std::env::temp_dir().join(format!(
+        "binarygrep_test_symbolic_links_{}",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("tree/sub"))?;
+    std::fs::create_dir_all(dir.join("other"))?;
+    std::fs::write(dir.join("tree/sub/a.bin"), [0x00, 0xc3, 0xdf])?;
+    std::fs::write(dir.join("other/b.bin"), [0x00, 0xc3, 0xdf])?;
+    std::os::unix::fs::symlink("..", dir.join("tree/sub/loop"))?;
+    std::os::unix::fs::symlink("../other", dir.join("tree/other"))?;
+    std::os::unix::fs::symlink("tree", dir.join("link"))?;
+    let search = |args: &[&str]| -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+        cmd.current_dir(&dir)
+            .args(["--no-ascii", "--no-offset"])
+            .args(args)
+            .arg("c3df")
+            .arg("link");
+        let output = String::from_utf8(cmd.assert().success().get_output().stdout.clone())?;
+        Ok(output.lines().map(String::from).collect())
+    };
+    assert_eq!(search(&["-r"])?, ["link/sub/a.bin c3df"]);
+    assert_eq!(
+        search(&["-R"])?,
+        ["link/other/b.bin c3df", "link/sub/a.bin c3df"]
+    );
+    assert_eq!(search(&["-r", "--no-follow"])?, Vec::<String>::new());
+    assert_eq!(
+        search(&["-R", "--one-file-system"])?,
+        ["link/other/b.bin c3df", "link/sub/a.bin c3df"]
+    );
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+    cmd.args(["-R", "--no-follow", "c3df", "tests"]);
+    cmd.assert().failure();
+    // Directories given more than once are searched each time
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+    cmd.current_dir(&dir).args([
+        "-r",
+        "--no-ascii",
+        "--no-offset",
+        "c3df",
+        "tree",
+        "tree/sub",
+        "tree",
+    ]);
+    cmd.assert()
+        .success()
+        .stdout("tree/sub/a.bin c3df\ntree/sub/a.bin c3df\ntree/sub/a.bin c3df\n");
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_devices() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = std::env::temp_dir().join(format!("binarygrep_test_devices_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir)?;
+    std::fs::write(dir.join("a.bin"), [0x00, 0xc3, 0xdf])?;
+    std::os::unix::fs::symlink("/dev/zero", dir.join("zero"))?;
+    assert!(
+        std::process::Command::new("mkfifo")
+            .arg(dir.join("fifo"))
+            .status()?
+            .success()
+    );
+    let search = |args: &[&str]| -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+        cmd.current_dir(&dir)
+            .args(["-R", "--no-ascii", "--length", "4"])
+            .args(args)
+            .arg(".");
+        let output = String::from_utf8(cmd.assert().success().get_output().stdout.clone())?;
+        Ok(output.lines().map(String::from).collect())
+    };
+    // Neither the infinite /dev/zero nor the FIFO without writer are read by default
+    assert_eq!(search(&["00c3"])?, ["./a.bin 00000000: 00c3"]);
+    assert_eq!(
+        search(&["--devices", "read", "0000"])?,
+        [
+            "./zero 00000000: 0000",
+            "./zero 00000001: 0000",
+            "./zero 00000002: 0000"
+        ]
+    );
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+    cmd.args(["--no-ascii", "--length", "2", "0000", "/dev/zero"]);
+    cmd.assert().success().stdout("00000000: 0000\n");
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+    cmd.args(["--devices", "skip", "0000", "/dev/zero"]);
+    cmd.assert().success().stdout("");
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_sort() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = std::env::temp_dir().join(format!("binarygrep_test_sort_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("b"))?;
+    let now = std::time::SystemTime::now();
+    for (i, (file, size)) in [("c.bin", 3), ("a.bin", 5), ("b/d.bin", 2), ("b/a.bin", 4)]
+        .into_iter()
+        .enumerate()
+    {
+        let mut data = vec![0xc3; size];
+        data[size - 1] = 0xdf;
+        let f = std::fs::File::create(dir.join(file))?;
+        std::io::Write::write_all(&mut &f, &data)?;
+        f.set_modified(now - std::time::Duration::from_secs(100 * i as u64))?;
+    }
+    std::fs::File::open(dir.join("b"))?.set_modified(now - std::time::Duration::from_secs(150))?;
+    let search = |sort: &str| -> Result<String, Box<dyn std::error::Error>> {
+        let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+        cmd.current_dir(&dir)
+            .args(["-r", "--no-ascii", "--no-offset", "--sort", sort, "df", "."])
+            .arg("c.bin");
+        Ok(String::from_utf8(
+            cmd.assert().success().get_output().stdout.clone(),
+        )?)
+    };
+    assert_eq!(
+        search("path")?,
+        "./a.bin df\n./b/a.bin df\n./b/d.bin df\n./c.bin df\nc.bin df\n"
+    );
+    assert_eq!(
+        search("size")?,
+        "./c.bin df\n./a.bin df\n./b/d.bin df\n./b/a.bin df\nc.bin df\n"
+    );
+    assert_eq!(
+        search("mtime")?,
+        "./b/a.bin df\n./b/d.bin df\n./a.bin df\n./c.bin df\nc.bin df\n"
+    );
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_decompress() -> Result<(), Box<dyn std::error::Error>> {
+    for file in [
+        "tests/testdata_783.gz",
+        "tests/testdata_783.xz",
+        "tests/testdata_783.bz2",
+        "tests/testdata_783.zst",
+        "tests/testdata_783",
+    ] {
+        let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+        cmd.args([
+            "-z",
+            "-H",
+            "--no-ascii",
+            "--skip",
+            "0x100",
+            "-B2",
+            "05",
+            file,
+        ]);
+        cmd.assert().success().stdout(format!(
+            "{file} 00000192: 602b05\n{file} 000001a0: ea5505\n{file} 0000028e: 375905\n"
+        ));
+    }
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+    cmd.args(["-z", "--no-ascii", "c3df"])
+        .pipe_stdin("tests/testdata_783.xz")?;
+    cmd.assert().success().stdout("00000256: c3df\n");
+    // The size of the decompressed data is unknown
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+    cmd.args(["-z", "--skip", "-10", "c3df", "tests/testdata_783.gz"]);
+    cmd.assert().failure();
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+    cmd.args([
+        "-z",
+        "--no-ascii",
+        "--skip",
+        "-200",
+        "c3df",
+        "tests/testdata_783",
+    ]);
+    cmd.assert().success().stdout("00000256: c3df\n");
+    Ok(())
+}
+
+#[test]
+fn test_decompress_invalid() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = std::env::temp_dir().join("binarygrep_test_decompress_invalid");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir)?;
+    // Data that only starts like compressed data is searched as it is
+    std::fs::write(dir.join("a.txt"), b"BZhello \xc3\xdf")?;
+    std::fs::write(dir.join("b.bin"), b"\x1f\x8b\xc3\xdf")?;
+    std::fs::copy("tests/testdata_783", dir.join("c.bin"))?;
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+    cmd.args(["-r", "-z", "--no-ascii", "c3df"]).arg(&dir);
+    let dir = dir.to_str().unwrap();
+    cmd.assert().success().stdout(format!(
+        "{dir}/a.txt 00000008: c3df\n{dir}/b.bin 00000002: c3df\n{dir}/c.bin 00000256: c3df\n"
+    ));
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+    cmd.args(["-z", "--no-ascii", "c3df"])
+        .write_stdin(&b"BZhello \xc3\xdf"[..]);
+    cmd.assert().success().stdout("00000008: c3df\n");
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_archives() -> Result<(), Box<dyn std::error::Error>> {
+    use std::io::Write;
+    let dir = std::env::temp_dir().join(format!("binarygrep_test_archives_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir)?;
+    let data = std::fs::read("tests/testdata_783")?;
+    let mut tar = tar::Builder::new(vec![]);
+    let mut header = tar::Header::new_ustar();
+    header.set_size(data.len() as u64);
+    header.set_cksum();
+    tar.append_data(&mut header, "fw/image.bin", &data[..])?;
+    let tar = tar.into_inner()?;
+    let mut zip = zip::ZipWriter::new(std::io::Cursor::new(vec![]));
+    let options = zip::write::SimpleFileOptions::default();
+    zip.start_file("image.bin", options)?;
+    zip.write_all(&data)?;
+    zip.start_file("nested.tar", options)?;
+    zip.write_all(&tar)?;
+    std::fs::write(dir.join("update.zip"), zip.finish()?.into_inner())?;
+    let mut gz = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+    gz.write_all(&tar)?;
+    std::fs::write(dir.join("update.tar.gz"), gz.finish()?)?;
+    let search = |args: &[&str]| -> Result<String, Box<dyn std::error::Error>> {
+        let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+        cmd.current_dir(&dir)
+            .args(["--no-ascii", "--archives", "c3df"])
+            .args(args);
+        Ok(String::from_utf8(
+            cmd.assert().success().get_output().stdout.clone(),
+        )?)
+    };
+    assert_eq!(
+        search(&["update.zip"])?,
+        "update.zip!image.bin 00000256: c3df\nupdate.zip!nested.tar!fw/image.bin 00000256: c3df\n"
+    );
+    assert_eq!(
+        search(&["--archive-depth", "1", "update.zip"])?,
+        // The nested archive is searched as it is
+        "update.zip!image.bin 00000256: c3df\nupdate.zip!nested.tar 00000456: c3df\n"
+    );
+    assert_eq!(
+        search(&["-z", "update.tar.gz"])?,
+        "update.tar.gz!fw/image.bin 00000256: c3df\n"
+    );
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+    cmd.args(["--no-ascii", "--archives", "c3df"])
+        .pipe_stdin(dir.join("update.zip"))?;
+    cmd.assert()
+        .success()
+        .stdout("-!image.bin 00000256: c3df\n-!nested.tar!fw/image.bin 00000256: c3df\n");
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+    cmd.args(["--no-ascii", "--archives", "c3df", "tests/testdata_783"]);
+    cmd.assert()
+        .success()
+        .stdout("tests/testdata_783 00000256: c3df\n");
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_archives_invalid() -> Result<(), Box<dyn std::error::Error>> {
+    use std::io::Write;
+    let dir = std::env::temp_dir().join("binarygrep_test_archives_invalid");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir)?;
+    // Data that only starts like an archive is searched as it is
+    let blob = b"PK\x03\x04firmware \xc3\xdf";
+    std::fs::write(dir.join("a.bin"), blob)?;
+    let mut gz = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+    gz.write_all(blob)?;
+    std::fs::write(dir.join("b.bin.gz"), gz.finish()?)?;
+    std::fs::write(dir.join("c.txt"), b"070701 \xc3\xdf")?;
+    std::fs::copy("tests/testdata_783", dir.join("d.bin"))?;
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+    cmd.current_dir(&dir)
+        .args(["-r", "-z", "--archives", "--no-ascii", "c3df", "."]);
+    cmd.assert().success().stdout(
+        "./a.bin 0000000d: c3df\n./b.bin.gz 0000000d: c3df\n./c.txt 00000007: c3df\n./d.bin 00000256: c3df\n",
+    );
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("binarygrep");
+    cmd.args(["--archives", "--no-ascii", "c3df"])
+        .write_stdin(&blob[..]);
+    cmd.assert().success().stdout("- 0000000d: c3df\n");
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_pid() -> Result<(), Box<dyn std::error::Error>> {
+    let marker = "bgrep-pid-marker-5f3c9a";
+    let pattern: String = marker.bytes().map(|b| format!("{b:02x}")).collect();
+    let search = |args: &str| -> Result<String, Box<dyn std::error::Error>> {
+        // binarygrep replaces the shell and thereby becomes the parent of the process it searches,
+        // which is required to read its memory if ptrace is restricted.  The environment of the
+        // child contains the marker, which is searched once the child has started sleep.
+        let output = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(format!(
+                "sleep 5 >/dev/null 2>&1 &
+                 while [ \"$(readlink /proc/$!/exe)\" = \"$(readlink /proc/$$/exe)\" ]; do :; done
+                 exec \"$0\" --no-offset --pid $! {} {}",
+                args, pattern
+            ))
+            .arg(assert_cmd::cargo::cargo_bin!("binarygrep"))
+            .env("BGREP_MARKER", marker)
+            .output()?;
+        assert!(output.status.success());
+        Ok(String::from_utf8(output.stdout)?)
+    };
+    let output = search("--map-name stack")?;
+    assert!(!output.is_empty());
+    for line in output.lines() {
+        assert_eq!(line, format!("[stack] rw-p {pattern}  {marker}"));
+    }
+    assert_eq!(search("--map-name stack --maps r-x")?, "");
+    assert_eq!(search("--map-name stack --maps rw-p")?, output);
+    assert_eq!(search("--map-name stack --maps '??-'")?, output);
+    assert_eq!(search("--map-name stack --maps '??x'")?, "");
+    Ok(())
+}